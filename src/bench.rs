@@ -0,0 +1,137 @@
+use anyhow::Context;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::events::Event;
+
+const ALL_SERVERS: &str = "all servers";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy)]
+pub struct Stats {
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+}
+
+pub type Baseline = BTreeMap<String, Stats>;
+
+#[derive(Default)]
+struct Samples {
+    run_started: Option<Instant>,
+    started: HashMap<String, Instant>,
+    ready_ms: BTreeMap<String, Vec<f64>>,
+}
+
+#[derive(Default)]
+pub struct Bench {
+    samples: Mutex<Samples>,
+}
+
+impl Bench {
+    pub fn start_run(&self) {
+        let mut samples = self.samples.lock().unwrap();
+
+        samples.run_started = Some(Instant::now());
+        samples.started.clear();
+    }
+
+    pub fn handle(&self, event: &Event) {
+        let mut samples = self.samples.lock().unwrap();
+
+        match event {
+            Event::ServerStarting { name } => {
+                samples.started.insert(name.to_string(), Instant::now());
+            }
+            Event::ServerReady { name } => {
+                if let Some(started) = samples.started.get(name) {
+                    let ms = started.elapsed().as_secs_f64() * 1000.0;
+
+                    samples
+                        .ready_ms
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(ms);
+                }
+            }
+            Event::ServersReady => {
+                if let Some(started) = samples.run_started {
+                    let ms = started.elapsed().as_secs_f64() * 1000.0;
+
+                    samples
+                        .ready_ms
+                        .entry(ALL_SERVERS.to_string())
+                        .or_default()
+                        .push(ms);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> Baseline {
+        self.samples
+            .lock()
+            .unwrap()
+            .ready_ms
+            .iter()
+            .map(|(name, samples)| (name.to_string(), stats(samples)))
+            .collect()
+    }
+}
+
+fn stats(samples: &[f64]) -> Stats {
+    let mut sorted = samples.to_vec();
+
+    sorted.sort_by(f64::total_cmp);
+
+    let rank = ((sorted.len() as f64 * 0.95).ceil() as usize).clamp(1, sorted.len());
+
+    Stats {
+        mean_ms: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p95_ms: sorted[rank - 1],
+    }
+}
+
+pub fn print(stats: &Baseline, baseline: Option<&Baseline>) {
+    let width = stats.keys().map(String::len).max().unwrap_or_default();
+
+    for (name, stats) in stats {
+        let comparison = baseline
+            .and_then(|baseline| baseline.get(name))
+            .map(|base| {
+                format!(
+                    "  (mean {:+.1}%, p95 {:+.1}%)",
+                    change(base.mean_ms, stats.mean_ms),
+                    change(base.p95_ms, stats.p95_ms)
+                )
+            })
+            .unwrap_or_default();
+
+        println!(
+            "{:width$}  mean {:>8.1}ms  p95 {:>8.1}ms{}",
+            name,
+            stats.mean_ms,
+            stats.p95_ms,
+            comparison,
+            width = width
+        );
+    }
+}
+
+fn change(before: f64, after: f64) -> f64 {
+    (after - before) / before.max(f64::EPSILON) * 100.0
+}
+
+pub fn load_baseline(path: &Path) -> anyhow::Result<Baseline> {
+    let contents =
+        fs::read_to_string(path).context(format!("Could not read baseline {}", path.display()))?;
+
+    serde_json::from_str(&contents).context(format!("Could not parse baseline {}", path.display()))
+}
+
+pub fn save_baseline(path: &Path, stats: &Baseline) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(stats)?)
+        .context(format!("Could not write baseline {}", path.display()))
+}
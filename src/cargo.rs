@@ -0,0 +1,92 @@
+use anyhow::{bail, Context};
+use log::info;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::config::Server;
+
+#[derive(serde::Deserialize)]
+struct Message {
+    reason: String,
+    executable: Option<PathBuf>,
+    target: Option<Target>,
+}
+
+#[derive(serde::Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+}
+
+fn build(server: &Server) -> anyhow::Result<PathBuf> {
+    let mut cmd = Command::new("cargo");
+
+    cmd.args(["build", "--message-format", "json-render-diagnostics"]);
+
+    if let Some(package) = &server.package {
+        cmd.args(["--package", package]);
+    }
+
+    if let Some(bin) = &server.bin {
+        cmd.args(["--bin", bin]);
+    }
+
+    if server.release {
+        cmd.arg("--release");
+    }
+
+    info!("Building server {} with cargo", server.name);
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Could not start cargo build")?;
+    let mut executable = None;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let message: Message = match serde_json::from_str(&line?) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+
+            if message.reason != "compiler-artifact" {
+                continue;
+            }
+
+            let is_bin = message.target.is_some_and(|target| {
+                target.kind.iter().any(|kind| kind == "bin")
+                    && server.bin.as_ref().is_none_or(|bin| bin == &target.name)
+            });
+
+            if is_bin && message.executable.is_some() {
+                executable = message.executable;
+            }
+        }
+    }
+
+    let status = child.wait()?;
+
+    if !status.success() {
+        bail!(
+            "Could not build server {}, cargo exited with {}",
+            server.name,
+            status
+        );
+    }
+
+    executable.context(format!(
+        "Cargo did not build a binary for server {}",
+        server.name
+    ))
+}
+
+pub fn run_command(server: &Server) -> anyhow::Result<(Command, String)> {
+    let executable = build(server)?;
+    let mut cmd = Command::new(&executable);
+
+    cmd.args(&server.args);
+
+    Ok((cmd, executable.display().to_string()))
+}
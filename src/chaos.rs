@@ -0,0 +1,105 @@
+use log::warn;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::server_management::ServerManager;
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChaosAction {
+    Restart,
+    Kill,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Chaos {
+    pub servers: Vec<String>,
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    #[serde(default = "default_actions")]
+    pub actions: Vec<ChaosAction>,
+}
+
+fn default_probability() -> f64 {
+    1.0
+}
+
+fn default_actions() -> Vec<ChaosAction> {
+    vec![ChaosAction::Restart]
+}
+
+pub struct ChaosMonkey {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ChaosMonkey {
+    pub fn start(server_manager: ServerManager) -> ChaosMonkey {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let Some(chaos) = &server_manager.config().chaos else {
+                return;
+            };
+
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(chaos.interval) {
+                if random() >= chaos.probability {
+                    continue;
+                }
+
+                let name = &chaos.servers[random_index(chaos.servers.len())];
+                let action = chaos.actions[random_index(chaos.actions.len())];
+                let result = match action {
+                    ChaosAction::Restart => {
+                        warn!("Chaos: restarting server {}", name);
+                        server_manager.restart(name)
+                    }
+                    ChaosAction::Kill => {
+                        warn!("Chaos: killing server {}", name);
+                        server_manager.stop_one(name)
+                    }
+                };
+
+                if let Err(e) = result {
+                    warn!("Chaos: {:#}", e);
+                }
+            }
+        });
+
+        ChaosMonkey {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for ChaosMonkey {
+    fn drop(&mut self) {
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn random_index(len: usize) -> usize {
+    ((random() * len as f64) as usize).min(len - 1)
+}
@@ -0,0 +1,480 @@
+use anyhow::{bail, Context};
+use log::info;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::chaos::Chaos;
+use crate::constants::{DEFAULT_CHECK_INTERVAL, DEFAULT_FAILURE_OUTPUT_LINES};
+use crate::docker::Compose;
+use crate::mock::MockRoute;
+use crate::notifications::Notifications;
+use crate::process::{Output, TimestampFormat};
+use crate::proxy::Route;
+use crate::retention::Retention;
+
+#[derive(serde::Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerType {
+    #[default]
+    Command,
+    Docker,
+    Compose,
+    #[serde(rename = "npm-script")]
+    NpmScript,
+    Cargo,
+    Task,
+    Static,
+    Mock,
+}
+
+#[derive(serde::Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckMethod {
+    #[default]
+    Get,
+    Head,
+}
+
+#[derive(serde::Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckType {
+    #[default]
+    Http,
+    Tcp,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Server {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub server_type: ServerType,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub command: String,
+    pub image: Option<String>,
+    pub container: Option<String>,
+    pub service: Option<String>,
+    pub package_dir: Option<PathBuf>,
+    pub script: Option<String>,
+    pub package: Option<String>,
+    pub bin: Option<String>,
+    #[serde(default)]
+    pub release: bool,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub host: Option<String>,
+    #[serde(default)]
+    pub forward: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default)]
+    pub output: Output,
+    #[serde(default, with = "humantime_serde")]
+    pub check_interval: Option<Duration>,
+    #[serde(default)]
+    pub check_method: CheckMethod,
+    #[serde(default)]
+    pub check: CheckType,
+    pub route: Option<Route>,
+    pub dir: Option<PathBuf>,
+    #[serde(default)]
+    pub spa: bool,
+    #[serde(default)]
+    pub routes: Vec<MockRoute>,
+    pub openapi: Option<PathBuf>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Stage {
+    pub name: String,
+    #[serde(default)]
+    pub stop: Vec<String>,
+    #[serde(default)]
+    pub restart: Vec<String>,
+    pub command: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ParallelCommand {
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct Commands {
+    #[serde(default)]
+    pub parallel: Vec<ParallelCommand>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Config {
+    pub servers: Vec<Server>,
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub stages: Vec<Stage>,
+    #[serde(default)]
+    pub commands: Commands,
+    pub chaos: Option<Chaos>,
+    pub proxy_port: Option<u16>,
+    pub timestamps: Option<TimestampFormat>,
+    #[serde(default, with = "humantime_serde")]
+    pub sort_window: Duration,
+    pub log_dir: Option<PathBuf>,
+    #[serde(default = "default_failure_output_lines")]
+    pub failure_output_lines: usize,
+    pub log_retention: Option<Retention>,
+    #[serde(default)]
+    pub quiet_servers: bool,
+    #[serde(default)]
+    pub quiet_command: bool,
+    pub control_port: Option<u16>,
+    pub control_socket: Option<PathBuf>,
+    #[serde(default)]
+    pub notifications: Notifications,
+    #[serde(default)]
+    pub compose: Compose,
+    pub base_url: Option<String>,
+    pub urls_file: Option<PathBuf>,
+    #[serde(default)]
+    pub github_output: bool,
+    pub snapshot_file: Option<PathBuf>,
+    pub history_file: Option<PathBuf>,
+    #[serde(default)]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    pub ready_port: Option<u16>,
+    pub ready_file: Option<PathBuf>,
+    pub check_concurrency: Option<usize>,
+    #[serde(default = "default_check_interval", with = "humantime_serde")]
+    pub check_interval: Duration,
+    #[serde(default, with = "humantime_serde")]
+    pub check_timeout: Option<Duration>,
+    #[serde(default, with = "humantime_serde")]
+    pub startup_timeout: Option<Duration>,
+}
+
+fn default_failure_output_lines() -> usize {
+    DEFAULT_FAILURE_OUTPUT_LINES
+}
+
+fn default_check_interval() -> Duration {
+    DEFAULT_CHECK_INTERVAL
+}
+
+fn normalize_url(name: &str, url: &str, check: &CheckType) -> anyhow::Result<String> {
+    let parsed =
+        reqwest::Url::parse(url).context(format!("Server {} has an invalid url {}", name, url))?;
+
+    match (parsed.scheme(), check) {
+        ("http" | "https", _) | ("tcp", CheckType::Tcp) => {}
+        (scheme, CheckType::Http) => bail!(
+            "Server {} has an unsupported url scheme {}, use http or https",
+            name,
+            scheme
+        ),
+        (scheme, CheckType::Tcp) => bail!(
+            "Server {} has an unsupported url scheme {}, use tcp, http or https",
+            name,
+            scheme
+        ),
+    }
+
+    if parsed.host_str().is_none_or(str::is_empty) {
+        bail!("Server {} has no host in url {}", name, url);
+    }
+
+    if *check == CheckType::Tcp && parsed.port_or_known_default().is_none() {
+        bail!("Server {} has no port in url {}", name, url);
+    }
+
+    if parsed.path() == "/" && parsed.query().is_none() && parsed.fragment().is_none() {
+        return Ok(parsed.as_str().trim_end_matches('/').to_string());
+    }
+
+    Ok(parsed.to_string())
+}
+
+impl Server {
+    pub fn service(&self) -> &str {
+        self.service.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl Config {
+    pub fn load(filename: &str) -> anyhow::Result<Config> {
+        let mut config = Config::read(filename)?;
+
+        config.validate(false)?;
+
+        Ok(config)
+    }
+
+    pub fn load_external(filename: &str) -> anyhow::Result<Config> {
+        let mut config = Config::read(filename)?;
+
+        config.validate(true)?;
+
+        Ok(config)
+    }
+
+    fn read(filename: &str) -> anyhow::Result<Config> {
+        let cwd = env::current_dir()?;
+        let tmp_path = cwd.join(filename);
+        let config_file_path = tmp_path.to_str().context(format!(
+            "Could not create String from Path {}",
+            tmp_path.display()
+        ))?;
+
+        info!("Loading config file {}", config_file_path);
+
+        let settings = config::Config::builder()
+            .add_source(config::File::new(
+                config_file_path,
+                config::FileFormat::Yaml,
+            ))
+            .build()
+            .context(format!("Could not find config file {}", filename))?;
+
+        settings
+            .try_deserialize::<Config>()
+            .context(format!("Could not parse config file {}", filename))
+    }
+
+    pub fn from_urls(urls: &[String]) -> anyhow::Result<Config> {
+        let servers: Vec<_> = urls
+            .iter()
+            .map(|url| {
+                let check = if url.starts_with("tcp://") {
+                    "tcp"
+                } else {
+                    "http"
+                };
+
+                serde_json::json!({ "name": url, "url": url, "check": check })
+            })
+            .collect();
+        let mut config: Config = serde_json::from_value(serde_json::json!({ "servers": servers }))?;
+
+        for server in &mut config.servers {
+            if let Some(url) = &server.url {
+                server.url = Some(normalize_url(&server.name, url, &server.check)?);
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn validate(&mut self, external: bool) -> anyhow::Result<()> {
+        if self.check_concurrency == Some(0) {
+            bail!("check_concurrency must be at least 1");
+        }
+
+        if self.check_interval.is_zero() {
+            bail!("check_interval must be above zero");
+        }
+
+        if !external
+            && self.command.is_empty()
+            && self.stages.is_empty()
+            && self.commands.parallel.is_empty()
+        {
+            bail!("No command, commands or stages to run");
+        }
+
+        if !self.command.is_empty() && !self.commands.parallel.is_empty() {
+            bail!("Use either command or commands, not both");
+        }
+
+        for command in &self.commands.parallel {
+            if command.command.is_empty() {
+                bail!("Parallel command {} has no command", command.name);
+            }
+        }
+
+        if self.command.is_empty() && !self.matrix.is_empty() {
+            bail!("matrix needs a command");
+        }
+
+        if let Some((key, _)) = self.matrix.iter().find(|(_, values)| values.is_empty()) {
+            bail!("Matrix variable {} has no values", key);
+        }
+
+        let mut names = HashSet::new();
+
+        for server in &mut self.servers {
+            server.name = server.name.trim().to_string();
+
+            if server.name.is_empty() {
+                bail!("Server names must not be empty");
+            }
+
+            if !names.insert(server.name.clone()) {
+                bail!("Duplicate server name {}", server.name);
+            }
+
+            if server
+                .check_interval
+                .is_some_and(|interval| interval.is_zero())
+            {
+                bail!("Server {} needs a check_interval above zero", server.name);
+            }
+        }
+
+        if let Some(name) = &mut self.base_url {
+            *name = name.trim().to_string();
+
+            match self.servers.iter().find(|s| &s.name == name) {
+                Some(server) if server.url.is_some() => {}
+                Some(_) => bail!("Server {} has no url to use as base_url", name),
+                None => bail!("Unknown server {} in base_url", name),
+            }
+        }
+
+        for stage in &mut self.stages {
+            for name in stage.stop.iter_mut().chain(&mut stage.restart) {
+                *name = name.trim().to_string();
+
+                match self.servers.iter().find(|s| &s.name == name) {
+                    Some(server) if server.server_type == ServerType::Task => {
+                        bail!(
+                            "Task {} cannot be stopped or restarted in stage {}",
+                            name,
+                            stage.name
+                        )
+                    }
+                    Some(_) => {}
+                    None => bail!("Unknown server {} in stage {}", name, stage.name),
+                }
+            }
+        }
+
+        if let Some(chaos) = &mut self.chaos {
+            if chaos.servers.is_empty() || chaos.actions.is_empty() {
+                bail!("chaos needs at least one server and one action");
+            }
+
+            if chaos.interval.is_zero() || !(0.0..=1.0).contains(&chaos.probability) {
+                bail!("chaos needs an interval above zero and a probability between 0 and 1");
+            }
+
+            for name in &mut chaos.servers {
+                *name = name.trim().to_string();
+
+                match self.servers.iter().find(|s| &s.name == name) {
+                    Some(server) if server.server_type == ServerType::Task => {
+                        bail!("Task {} cannot be used for chaos", name)
+                    }
+                    Some(_) => {}
+                    None => bail!("Unknown server {} in chaos", name),
+                }
+            }
+        }
+
+        for server in &mut self.servers {
+            if let Some(url) = &server.url {
+                server.url = Some(normalize_url(&server.name, url, &server.check)?);
+            }
+
+            if let Some(route) = &server.route {
+                if server.url.is_none() {
+                    bail!(
+                        "Server {} needs a url to be routed by the proxy",
+                        server.name
+                    );
+                }
+
+                if route.path.is_none() && route.host.is_none() {
+                    bail!("Route of server {} needs a path or a host", server.name);
+                }
+            }
+
+            match server.server_type {
+                ServerType::Command => {
+                    if !external && server.command.is_empty() {
+                        bail!("Server {} has no command", server.name);
+                    }
+
+                    if server.url.is_none() {
+                        bail!("Server {} has no url", server.name);
+                    }
+                }
+                ServerType::Docker => {
+                    if !external && server.image.is_none() {
+                        bail!("Server {} has no image", server.name);
+                    }
+                }
+                ServerType::Compose => {}
+                ServerType::Task => {
+                    if !external && server.command.is_empty() {
+                        bail!("Task {} has no command", server.name);
+                    }
+                }
+                ServerType::NpmScript => {
+                    if server.script.is_none() {
+                        bail!("Server {} has no script", server.name);
+                    }
+
+                    if server.url.is_none() {
+                        bail!("Server {} has no url", server.name);
+                    }
+                }
+                ServerType::Cargo => {
+                    if server.url.is_none() {
+                        bail!("Server {} has no url", server.name);
+                    }
+                }
+                ServerType::Mock => {
+                    if server.routes.is_empty() && server.openapi.is_none() {
+                        bail!("Mock server {} has no routes or openapi file", server.name);
+                    }
+
+                    if server.url.is_none() {
+                        bail!("Server {} has no url", server.name);
+                    }
+                }
+                ServerType::Static => {
+                    if server.dir.is_none() {
+                        bail!("Server {} has no dir", server.name);
+                    }
+
+                    if server.url.is_none() {
+                        bail!("Server {} has no url", server.name);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn base_url(&self) -> Option<String> {
+        let name = self.base_url.as_ref()?;
+        let url = self
+            .servers
+            .iter()
+            .find(|s| &s.name == name)?
+            .url
+            .as_ref()?;
+
+        match reqwest::Url::parse(url) {
+            Ok(url) => Some(url.origin().ascii_serialization()),
+            Err(_) => Some(url.to_string()),
+        }
+    }
+
+    pub fn check_interval(&self, server: &Server) -> Duration {
+        server.check_interval.unwrap_or(self.check_interval)
+    }
+
+    pub fn uses_compose(&self) -> bool {
+        self.servers
+            .iter()
+            .any(|s| s.server_type == ServerType::Compose)
+    }
+}
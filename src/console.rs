@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static CONSOLE: OnceLock<Sender<Message>> = OnceLock::new();
+
+enum Message {
+    Line(Instant, Vec<u8>),
+    Flush(Sender<()>),
+}
+
+pub fn init(sort_window: Duration) {
+    CONSOLE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || write_lines(receiver, sort_window));
+
+        sender
+    });
+}
+
+pub fn write_line(captured: Instant, line: Vec<u8>) -> io::Result<()> {
+    match CONSOLE.get() {
+        Some(sender) => {
+            sender.send(Message::Line(captured, line)).ok();
+
+            Ok(())
+        }
+        None => io::stdout().lock().write_all(&line),
+    }
+}
+
+pub fn flush() {
+    if let Some(sender) = CONSOLE.get() {
+        let (done_sender, done_receiver) = mpsc::channel();
+
+        if sender.send(Message::Flush(done_sender)).is_ok() {
+            done_receiver.recv().ok();
+        }
+    }
+}
+
+fn write_lines(receiver: Receiver<Message>, sort_window: Duration) {
+    let mut pending: Vec<(Instant, Vec<u8>)> = Vec::new();
+
+    loop {
+        let message = match pending.iter().map(|(captured, _)| *captured).min() {
+            Some(oldest) => receiver
+                .recv_timeout((oldest + sort_window).saturating_duration_since(Instant::now())),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match message {
+            Ok(Message::Line(captured, line)) => pending.push((captured, line)),
+            Ok(Message::Flush(done)) => {
+                write_pending(&mut pending, None);
+                done.send(()).ok();
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                write_pending(&mut pending, None);
+                break;
+            }
+        }
+
+        write_pending(&mut pending, Some(sort_window));
+    }
+}
+
+fn write_pending(pending: &mut Vec<(Instant, Vec<u8>)>, sort_window: Option<Duration>) {
+    pending.sort_by_key(|(captured, _)| *captured);
+
+    let count = match sort_window {
+        Some(sort_window) => pending
+            .iter()
+            .take_while(|(captured, _)| captured.elapsed() >= sort_window)
+            .count(),
+        None => pending.len(),
+    };
+    let mut stdout = io::stdout().lock();
+
+    for (_, line) in pending.drain(..count) {
+        stdout.write_all(&line).ok();
+    }
+
+    stdout.flush().ok();
+}
@@ -0,0 +1,8 @@
+use std::time::Duration;
+
+pub const DEFAULT_CONFIG_FILE: &str = "servers.yaml";
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+pub const DEFAULT_FAILURE_OUTPUT_LINES: usize = 20;
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+pub const CONTROL_LOG_LINES: usize = 1000;
+pub const DEFAULT_CONTROL_SOCKET: &str = "server-runner.sock";
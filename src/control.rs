@@ -0,0 +1,286 @@
+use anyhow::{anyhow, bail, Context};
+use log::{info, warn};
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::server_management::ServerManager;
+
+const DEFAULT_LOG_LINES: usize = 100;
+
+pub fn serve<F>(port: u16, server_manager: ServerManager, shutdown: F) -> anyhow::Result<()>
+where
+    F: Fn() + Send + 'static,
+{
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("Could not start control API on port {}: {}", port, e))?;
+
+    info!("Control API listening on http://127.0.0.1:{}", port);
+    serve_http(server, server_manager, shutdown);
+
+    Ok(())
+}
+
+pub fn serve_listener<F>(
+    listener: TcpListener,
+    server_manager: ServerManager,
+    shutdown: F,
+) -> anyhow::Result<()>
+where
+    F: Fn() + Send + 'static,
+{
+    let address = listener.local_addr()?;
+    let server = tiny_http::Server::from_listener(listener, None)
+        .map_err(|e| anyhow!("Could not start control API on {}: {}", address, e))?;
+
+    info!("Control API listening on http://{}", address);
+    serve_http(server, server_manager, shutdown);
+
+    Ok(())
+}
+
+fn serve_http<F>(server: tiny_http::Server, server_manager: ServerManager, shutdown: F)
+where
+    F: Fn() + Send + 'static,
+{
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if handle_request(request, &server_manager) {
+                shutdown();
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+pub fn serve_socket<F>(
+    path: &Path,
+    server_manager: ServerManager,
+    shutdown: F,
+) -> anyhow::Result<()>
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            bail!(
+                "Control socket {} is already used by another instance",
+                path.display()
+            );
+        }
+
+        std::fs::remove_file(path).context(format!(
+            "Could not remove stale control socket {}",
+            path.display()
+        ))?;
+    }
+
+    let listener = UnixListener::bind(path).context(format!(
+        "Could not create control socket {}",
+        path.display()
+    ))?;
+
+    info!("Control socket listening on {}", path.display());
+    serve_unix_listener(listener, server_manager, shutdown);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn serve_unix_listener<F>(listener: UnixListener, server_manager: ServerManager, shutdown: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let shutdown = Arc::new(shutdown);
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let server_manager = server_manager.clone();
+            let shutdown = Arc::clone(&shutdown);
+
+            thread::spawn(move || {
+                if handle_connection(stream, &server_manager) {
+                    shutdown();
+                }
+            });
+        }
+    });
+}
+
+#[cfg(unix)]
+fn handle_connection(stream: UnixStream, server_manager: &ServerManager) -> bool {
+    let mut line = String::new();
+
+    if BufReader::new(&stream).read_line(&mut line).is_err() {
+        return false;
+    }
+
+    let mut parts = line.split_whitespace();
+    let mut shutdown_requested = false;
+    let result = match (parts.next(), parts.next()) {
+        (Some("status"), None) => {
+            serde_json::to_string(&server_manager.status()).map_err(anyhow::Error::from)
+        }
+        (Some("snapshot"), None) => {
+            serde_json::to_string_pretty(&server_manager.snapshot()).map_err(anyhow::Error::from)
+        }
+        (Some("restart"), Some(name)) => server_manager
+            .restart(name)
+            .map(|_| format!("Restarted server {}", name)),
+        (Some("stop"), None) => {
+            shutdown_requested = true;
+            Ok("Stopping servers".to_string())
+        }
+        _ => Err(anyhow!("Unknown command {}", line.trim())),
+    };
+    let response = match result {
+        Ok(body) => format!("ok\n{}", body),
+        Err(e) => format!("error\n{:#}", e),
+    };
+
+    if let Err(e) = (&stream).write_all(response.as_bytes()) {
+        warn!("Could not send control socket response: {}", e);
+    }
+
+    shutdown_requested
+}
+
+#[cfg(unix)]
+pub fn send(path: &Path, command: &str) -> anyhow::Result<String> {
+    let mut stream = UnixStream::connect(path).context(format!(
+        "Could not connect to control socket {}, is Server Runner running?",
+        path.display()
+    ))?;
+
+    stream.write_all(format!("{}\n", command).as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut response = String::new();
+
+    stream.read_to_string(&mut response)?;
+
+    match response.split_once('\n') {
+        Some(("ok", body)) => Ok(body.to_string()),
+        Some(("error", message)) => bail!("{}", message),
+        _ => bail!("Invalid response from control socket {}", path.display()),
+    }
+}
+
+fn handle_request(request: Request, server_manager: &ServerManager) -> bool {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    // Browsers add an Origin header to cross-origin requests, so web pages can't control the
+    // servers through the API.
+    let cross_origin = request
+        .headers()
+        .iter()
+        .any(|header| header.field.equiv("Origin"));
+    let mut shutdown_requested = false;
+
+    let response = match (request.method(), segments.as_slice()) {
+        _ if cross_origin => text_response(403, "Cross-origin requests are not allowed"),
+        (Method::Get, ["status"]) => json_response(200, &server_manager.status()),
+        (Method::Get, ["snapshot"]) => json_response(200, &server_manager.snapshot()),
+        (Method::Post, ["shutdown"]) => {
+            shutdown_requested = true;
+            text_response(202, "Shutting down")
+        }
+        (Method::Post, ["servers", name, "restart"]) => {
+            let name = decode(name);
+
+            match server_manager.restart(&name) {
+                Ok(_) => text_response(200, &format!("Restarted server {}", name)),
+                Err(e) => error_response(server_manager, &name, e),
+            }
+        }
+        (Method::Get, ["logs", name]) => {
+            let name = decode(name);
+            let count = query
+                .split('&')
+                .filter_map(|param| param.strip_prefix("tail="))
+                .find_map(|count| count.parse().ok())
+                .unwrap_or(DEFAULT_LOG_LINES);
+
+            match server_manager.logs(&name, count) {
+                Ok(lines) => json_response(200, &lines),
+                Err(e) => error_response(server_manager, &name, e),
+            }
+        }
+        _ => text_response(404, "Not found"),
+    };
+
+    if let Err(e) = request.respond(response) {
+        warn!("Could not send control API response: {}", e);
+    }
+
+    shutdown_requested
+}
+
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn error_response(
+    server_manager: &ServerManager,
+    name: &str,
+    error: anyhow::Error,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let status = if server_manager
+        .config()
+        .servers
+        .iter()
+        .any(|s| s.name == name)
+    {
+        500
+    } else {
+        404
+    };
+
+    text_response(status, &format!("{:#}", error))
+}
+
+fn json_response<T: serde::Serialize>(
+    status: u16,
+    value: &T,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+fn text_response(status: u16, body: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body).with_status_code(status)
+}
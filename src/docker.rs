@@ -0,0 +1,246 @@
+use anyhow::{bail, Context};
+use log::info;
+use std::process::{Command, Stdio};
+
+use crate::config::Server;
+
+pub fn container_name(server: &Server) -> String {
+    if let Some(container) = &server.container {
+        return container.to_string();
+    }
+
+    let name: String = server
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    format!("server-runner-{}-{}", name, std::process::id())
+}
+
+pub fn run_command(server: &Server) -> Command {
+    let mut cmd = Command::new("docker");
+
+    cmd.args(["run", "--rm", "--name", &container_name(server)]);
+
+    for port in &server.ports {
+        cmd.args(["--publish", port]);
+    }
+
+    for env in &server.env {
+        cmd.args(["--env", env]);
+    }
+
+    for volume in &server.volumes {
+        cmd.args(["--volume", volume]);
+    }
+
+    cmd.arg(server.image.as_deref().unwrap_or_default());
+
+    cmd
+}
+
+pub fn is_healthy(container: &str) -> anyhow::Result<bool> {
+    let output = Command::new("docker")
+        .args([
+            "inspect",
+            "--format",
+            "{{.State.Status}} {{if .State.Health}}{{.State.Health.Status}}{{end}}",
+            container,
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .context("Could not run docker inspect")?;
+
+    if !output.status.success() {
+        return Ok(false);
+    }
+
+    Ok(is_running_and_healthy(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+pub fn stop(server: &Server) -> anyhow::Result<()> {
+    let name = container_name(server);
+
+    info!("Stopping container {}", name);
+
+    let status = Command::new("docker")
+        .args(["stop", &name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Could not run docker stop")?;
+
+    if !status.success() {
+        bail!("Could not stop container {}", name);
+    }
+
+    Ok(())
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct Compose {
+    pub file: Option<String>,
+    pub project_name: Option<String>,
+}
+
+impl Compose {
+    fn command(&self) -> Command {
+        let mut cmd = Command::new("docker");
+
+        cmd.arg("compose");
+
+        if let Some(file) = &self.file {
+            cmd.args(["--file", file]);
+        }
+
+        if let Some(project_name) = &self.project_name {
+            cmd.args(["--project-name", project_name]);
+        }
+
+        cmd
+    }
+
+    pub fn up(&self) -> anyhow::Result<()> {
+        info!("Starting docker compose services");
+
+        let status = self
+            .command()
+            .args(["up", "--detach"])
+            .status()
+            .context("Could not run docker compose up")?;
+
+        if !status.success() {
+            bail!("docker compose up failed with {}", status);
+        }
+
+        Ok(())
+    }
+
+    pub fn down(&self) -> anyhow::Result<()> {
+        info!("Stopping docker compose services");
+
+        let status = self
+            .command()
+            .arg("down")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Could not run docker compose down")?;
+
+        if !status.success() {
+            bail!("docker compose down failed with {}", status);
+        }
+
+        Ok(())
+    }
+
+    pub fn start_service(&self, service: &str) -> anyhow::Result<()> {
+        info!("Starting docker compose service {}", service);
+
+        let status = self
+            .command()
+            .args(["up", "--detach", service])
+            .status()
+            .context(format!("Could not run docker compose up for {}", service))?;
+
+        if !status.success() {
+            bail!("docker compose up {} failed with {}", service, status);
+        }
+
+        Ok(())
+    }
+
+    pub fn stop_service(&self, service: &str) -> anyhow::Result<()> {
+        info!("Stopping docker compose service {}", service);
+
+        let status = self
+            .command()
+            .args(["stop", service])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context(format!("Could not run docker compose stop for {}", service))?;
+
+        if !status.success() {
+            bail!("docker compose stop {} failed with {}", service, status);
+        }
+
+        Ok(())
+    }
+
+    pub fn up_command(&self, service: &str) -> Command {
+        let mut cmd = self.command();
+
+        cmd.args(["up", service]);
+
+        cmd
+    }
+
+    pub fn logs_command(&self, service: &str) -> Command {
+        let mut cmd = self.command();
+
+        cmd.args(["logs", "--follow", "--no-log-prefix", service]);
+
+        cmd
+    }
+
+    pub fn is_healthy(&self, service: &str) -> anyhow::Result<bool> {
+        let output = self
+            .command()
+            .args(["ps", "--quiet", service])
+            .stderr(Stdio::null())
+            .output()
+            .context("Could not run docker compose ps")?;
+
+        if !output.status.success() {
+            return Ok(false);
+        }
+
+        // A service with replicas runs several containers, all of them have to be healthy.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let containers: Vec<&str> = stdout.split_whitespace().collect();
+
+        if containers.is_empty() {
+            return Ok(false);
+        }
+
+        for container in containers {
+            if !is_healthy(container)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+fn is_running_and_healthy(state: &str) -> bool {
+    matches!(
+        state.split_whitespace().collect::<Vec<_>>().as_slice(),
+        ["running"] | ["running", "healthy"]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_running_and_healthy_state() {
+        assert!(is_running_and_healthy("running \n"));
+        assert!(is_running_and_healthy("running healthy\n"));
+        assert!(!is_running_and_healthy("running starting\n"));
+        assert!(!is_running_and_healthy("running unhealthy\n"));
+        assert!(!is_running_and_healthy("exited \n"));
+        assert!(!is_running_and_healthy(""));
+    }
+}
@@ -0,0 +1,73 @@
+use std::process::ExitStatus;
+use std::sync::Arc;
+
+#[derive(serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ServerStarting {
+        name: String,
+    },
+    ServerSpawned {
+        name: String,
+        pid: u32,
+    },
+    CheckAttempt {
+        name: String,
+        url: Option<String>,
+        attempt: u32,
+    },
+    CheckFinished {
+        name: String,
+        attempt: u32,
+        ready: bool,
+    },
+    ServerReady {
+        name: String,
+    },
+    ServerCrashed {
+        name: String,
+        #[serde(serialize_with = "serialize_status")]
+        status: ExitStatus,
+    },
+    ServersReady,
+    ReadinessFailed {
+        name: String,
+        error: String,
+    },
+    CommandStarted {
+        command: String,
+        index: usize,
+    },
+    CommandExited {
+        command: String,
+        index: usize,
+        #[serde(serialize_with = "serialize_status")]
+        status: ExitStatus,
+    },
+}
+
+fn serialize_status<S: serde::Serializer>(
+    status: &ExitStatus,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_some(&status.code())
+}
+
+pub type EventHandler = Arc<dyn Fn(&Event) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct Events {
+    handlers: Vec<EventHandler>,
+}
+
+impl Events {
+    pub fn add(&mut self, handler: EventHandler) {
+        self.handlers.push(handler);
+    }
+
+    pub fn emit(&self, event: Event) {
+        for handler in &self.handlers {
+            handler(&event);
+        }
+    }
+}
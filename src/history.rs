@@ -0,0 +1,175 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::events::Event;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ServerRun {
+    pub name: String,
+    pub attempts: u32,
+    pub ready_after_ms: Option<u64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Run {
+    pub id: usize,
+    pub started: String,
+    pub config_hash: String,
+    pub duration_ms: u64,
+    pub servers: Vec<ServerRun>,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+struct Recording {
+    servers: Vec<ServerRun>,
+    started_servers: HashMap<String, Instant>,
+    exit_code: Option<i32>,
+    error: Option<String>,
+}
+
+// FNV-1a, so hashes stay comparable across Rust versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+pub struct RunHistory {
+    path: PathBuf,
+    config_hash: String,
+    started: Instant,
+    started_at: OffsetDateTime,
+    recording: Mutex<Recording>,
+}
+
+impl RunHistory {
+    pub fn new(path: &Path, config_file: &str, started: Instant) -> anyhow::Result<RunHistory> {
+        let contents =
+            fs::read(config_file).context(format!("Could not read config file {}", config_file))?;
+
+        Ok(RunHistory {
+            path: path.to_path_buf(),
+            config_hash: format!("{:016x}", fnv1a(&contents)),
+            started,
+            started_at: OffsetDateTime::now_utc(),
+            recording: Mutex::new(Recording {
+                servers: Vec::new(),
+                started_servers: HashMap::new(),
+                exit_code: None,
+                error: None,
+            }),
+        })
+    }
+
+    pub fn handle(&self, event: &Event) {
+        let mut recording = self.recording.lock().unwrap();
+
+        match event {
+            Event::ServerStarting { name } => {
+                recording
+                    .started_servers
+                    .insert(name.to_string(), Instant::now());
+
+                if !recording.servers.iter().any(|s| &s.name == name) {
+                    recording.servers.push(ServerRun {
+                        name: name.to_string(),
+                        attempts: 0,
+                        ready_after_ms: None,
+                    });
+                }
+            }
+            Event::CheckAttempt { name, attempt, .. } => {
+                if let Some(server) = recording.servers.iter_mut().find(|s| &s.name == name) {
+                    server.attempts = *attempt;
+                }
+            }
+            Event::ServerReady { name } => {
+                let ready_after_ms = recording
+                    .started_servers
+                    .get(name)
+                    .map(|started| started.elapsed().as_millis() as u64);
+
+                if let Some(server) = recording.servers.iter_mut().find(|s| &s.name == name) {
+                    server.ready_after_ms = ready_after_ms;
+                }
+            }
+            Event::ReadinessFailed { error, .. } => recording.error = Some(error.to_string()),
+            Event::CommandExited { status, .. } => recording.exit_code = status.code(),
+            _ => {}
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let recording = self.recording.lock().unwrap();
+        let run = Run {
+            id: load(&self.path)?.last().map_or(1, |run| run.id + 1),
+            started: self.started_at.format(&Rfc3339)?,
+            config_hash: self.config_hash.to_string(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            servers: recording.servers.clone(),
+            exit_code: recording.exit_code,
+            error: recording.error.clone(),
+        };
+
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context(format!(
+                "Could not create history directory {}",
+                dir.display()
+            ))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!(
+                "Could not open history file {}",
+                self.path.display()
+            ))?;
+
+        writeln!(file, "{}", serde_json::to_string(&run)?).context(format!(
+            "Could not write history file {}",
+            self.path.display()
+        ))
+    }
+}
+
+pub fn load(path: &Path) -> anyhow::Result<Vec<Run>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(format!("Could not read history file {}", path.display())),
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .context(format!("Could not parse history file {}", path.display()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_config_with_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+}
@@ -0,0 +1,71 @@
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tiny_http::Request;
+
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(1);
+const RELEASE_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct InProcessServer {
+    server: Option<Arc<tiny_http::Server>>,
+    thread: Option<JoinHandle<()>>,
+    address: Option<SocketAddr>,
+}
+
+impl InProcessServer {
+    pub fn start<F>(server: tiny_http::Server, handle: F) -> InProcessServer
+    where
+        F: Fn(Request) + Send + 'static,
+    {
+        let address = server.server_addr().to_ip();
+        let server = Arc::new(server);
+        let incoming = Arc::clone(&server);
+        let thread = thread::spawn(move || {
+            for request in incoming.incoming_requests() {
+                handle(request);
+            }
+        });
+
+        InProcessServer {
+            server: Some(server),
+            thread: Some(thread),
+            address,
+        }
+    }
+
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        let Some(server) = self.server.take() else {
+            return;
+        };
+
+        server.unblock();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+
+        // tiny_http closes its listener on a thread of its own after the server is dropped.
+        drop(server);
+
+        if let Some(address) = self.address {
+            let released = Instant::now() + RELEASE_TIMEOUT;
+
+            while TcpListener::bind(address).is_err() && Instant::now() < released {
+                thread::sleep(RELEASE_INTERVAL);
+            }
+        }
+    }
+}
+
+impl Drop for InProcessServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
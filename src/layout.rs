@@ -0,0 +1,168 @@
+use std::process::Command;
+
+use crate::config::{Config, Server, ServerType};
+use crate::{docker, mock, npm, ssh, static_files};
+
+const SESSION: &str = "server-runner";
+
+pub struct Pane {
+    pub name: String,
+    pub command: String,
+}
+
+fn is_plain(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c))
+}
+
+fn shell_word(value: &str) -> String {
+    if is_plain(value) {
+        value.to_string()
+    } else {
+        ssh::quote(value)
+    }
+}
+
+fn render(cmd: &Command) -> String {
+    let words: Vec<String> = [cmd.get_program()]
+        .into_iter()
+        .chain(cmd.get_args())
+        .map(|word| shell_word(&word.to_string_lossy()))
+        .collect();
+
+    match cmd.get_current_dir() {
+        Some(dir) => format!(
+            "cd {} && {}",
+            shell_word(&dir.to_string_lossy()),
+            words.join(" ")
+        ),
+        None => words.join(" "),
+    }
+}
+
+fn cargo_command(server: &Server) -> Command {
+    let mut cmd = Command::new("cargo");
+
+    cmd.arg("run");
+
+    if let Some(package) = &server.package {
+        cmd.args(["--package", package]);
+    }
+
+    if let Some(bin) = &server.bin {
+        cmd.args(["--bin", bin]);
+    }
+
+    if server.release {
+        cmd.arg("--release");
+    }
+
+    if !server.args.is_empty() {
+        cmd.arg("--").args(&server.args);
+    }
+
+    cmd
+}
+
+fn server_command(config: &Config, server: &Server) -> anyhow::Result<String> {
+    let command = match server.server_type {
+        ServerType::Command | ServerType::Task => match &server.host {
+            Some(host) => render(&ssh::run_command(server, host)),
+            None => server.command.to_string(),
+        },
+        ServerType::Docker => render(&docker::run_command(server)),
+        ServerType::Compose => render(&config.compose.up_command(server.service())),
+        ServerType::NpmScript => render(&npm::run_command(server).0),
+        ServerType::Cargo => render(&cargo_command(server)),
+        ServerType::Static => render(&static_files::run_command(server)?),
+        ServerType::Mock => render(&mock::run_command(server)?),
+    };
+
+    Ok(command)
+}
+
+pub fn panes(config: &Config, config_file: &str) -> anyhow::Result<Vec<Pane>> {
+    let mut panes = config
+        .servers
+        .iter()
+        .map(|server| {
+            Ok(Pane {
+                name: server.name.to_string(),
+                command: server_command(config, server)?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let wait = format!(
+        "{} --config {} wait",
+        shell_word(&static_files::program().to_string_lossy()),
+        shell_word(config_file)
+    );
+
+    if !config.command.is_empty() {
+        panes.push(Pane {
+            name: "command".to_string(),
+            command: format!("{} && {}", wait, config.command),
+        });
+    }
+
+    for parallel in &config.commands.parallel {
+        panes.push(Pane {
+            name: parallel.name.to_string(),
+            command: format!("{} && {}", wait, parallel.command),
+        });
+    }
+
+    Ok(panes)
+}
+
+pub fn tmux(panes: &[Pane]) -> String {
+    let mut script = format!(
+        "#!/bin/sh\nset -e\n\nSESSION=\"${{SESSION:-{}}}\"\n\n",
+        SESSION
+    );
+
+    for (index, pane) in panes.iter().enumerate() {
+        let command = ssh::quote(&pane.command);
+
+        if index == 0 {
+            script.push_str(&format!(
+                "tmux new-session -d -s \"$SESSION\" -n {} {}\n",
+                SESSION, command
+            ));
+            script.push_str("tmux set-option -t \"$SESSION\" remain-on-exit on\n");
+            script.push_str("tmux set-option -t \"$SESSION\" pane-border-status top\n");
+        } else {
+            script.push_str(&format!("tmux split-window -t \"$SESSION\" {}\n", command));
+        }
+
+        script.push_str(&format!(
+            "tmux select-pane -t \"$SESSION\" -T {}\n",
+            shell_word(&pane.name)
+        ));
+        script.push_str("tmux select-layout -t \"$SESSION\" tiled\n");
+    }
+
+    script.push_str("\ntmux attach-session -t \"$SESSION\"\n");
+    script
+}
+
+fn kdl_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn zellij(panes: &[Pane]) -> String {
+    let mut layout = "layout {\n".to_string();
+
+    for pane in panes {
+        layout.push_str(&format!(
+            "    pane name={} command=\"sh\" {{\n        args \"-c\" {}\n    }}\n",
+            kdl_string(&pane.name),
+            kdl_string(&pane.command)
+        ));
+    }
+
+    layout.push_str("}\n");
+    layout
+}
@@ -0,0 +1,107 @@
+mod bench;
+mod cargo;
+mod chaos;
+mod config;
+mod console;
+mod constants;
+mod control;
+mod docker;
+mod events;
+mod history;
+mod in_process;
+mod layout;
+mod mock;
+mod nats;
+mod notifications;
+mod npm;
+mod process;
+mod profile;
+mod proxy;
+mod readiness_gate;
+mod retention;
+mod ring_buffer;
+mod server_management;
+mod ssh;
+mod static_files;
+#[cfg(unix)]
+mod syslog;
+#[cfg(unix)]
+mod systemd;
+mod telemetry;
+mod test_environment;
+
+pub use chaos::{Chaos, ChaosAction};
+pub use config::{
+    CheckMethod, CheckType, Commands, Config, ParallelCommand, Server, ServerType, Stage,
+};
+pub use events::Event;
+pub use mock::MockRoute;
+pub use notifications::{NatsSettings, NotificationEvent, Notifications, Webhook};
+pub use process::{Filter, Output, OutputMode, OutputSettings, TimestampFormat};
+pub use proxy::Route;
+pub use retention::Retention;
+pub use server_management::{
+    wait_for_external_servers, CheckRecord, ServerManager, ServerRunnerBuilder, ServerSnapshot,
+    ServerState, ServerStatus,
+};
+#[cfg(unix)]
+pub use syslog::Syslog;
+pub use test_environment::TestEnvironment;
+
+// Used by the server-runner binary, not part of the library API.
+#[doc(hidden)]
+pub mod cli {
+    pub mod bench {
+        pub use crate::bench::{load_baseline, print, save_baseline, Bench};
+    }
+
+    pub mod constants {
+        pub use crate::constants::{
+            CONTROL_LOG_LINES, DEFAULT_CONFIG_FILE, DEFAULT_CONTROL_SOCKET, DEFAULT_MAX_ATTEMPTS,
+        };
+    }
+
+    pub mod control {
+        pub use crate::control::{send, serve, serve_listener, serve_socket};
+
+        #[cfg(unix)]
+        pub use crate::control::serve_unix_listener;
+    }
+
+    pub mod history {
+        pub use crate::history::{load, RunHistory};
+    }
+
+    pub mod layout {
+        pub use crate::layout::{panes, tmux, zellij};
+    }
+
+    pub mod mock {
+        pub use crate::mock::serve;
+    }
+
+    pub mod notifications {
+        pub use crate::notifications::Notifier;
+    }
+
+    pub mod profile {
+        pub use crate::profile::StartupProfile;
+    }
+
+    pub mod proxy {
+        pub use crate::proxy::serve;
+    }
+
+    pub mod static_files {
+        pub use crate::static_files::serve;
+    }
+
+    #[cfg(unix)]
+    pub mod systemd {
+        pub use crate::systemd::{listen_sockets, notify, start_watchdog, ListenSocket};
+    }
+
+    pub mod telemetry {
+        pub use crate::telemetry::Tracer;
+    }
+}
@@ -1,258 +1,668 @@
-use anyhow::{bail, Context};
-use clap::Parser;
-use log::info;
-use std::collections::HashMap;
-use std::env;
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-use std::process::{Child, Command};
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Instant;
+
+use server_runner::cli::bench::{self, Bench};
+use server_runner::cli::constants::{
+    CONTROL_LOG_LINES, DEFAULT_CONFIG_FILE, DEFAULT_CONTROL_SOCKET, DEFAULT_MAX_ATTEMPTS,
+};
+use server_runner::cli::control;
+use server_runner::cli::history::{self, RunHistory};
+use server_runner::cli::layout;
+use server_runner::cli::mock;
+use server_runner::cli::notifications::Notifier;
+use server_runner::cli::profile::StartupProfile;
+use server_runner::cli::proxy;
+use server_runner::cli::static_files;
+#[cfg(unix)]
+use server_runner::cli::systemd::{self, ListenSocket};
+use server_runner::cli::telemetry::Tracer;
+use server_runner::{Config, Event, OutputSettings, ServerManager, ServerRunnerBuilder};
 
 #[derive(Parser)]
 #[command(version)]
 struct Args {
-    #[arg(short, long, default_value = "servers.yaml")]
+    #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
     config: String,
 
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
 
-    #[arg(short, long, default_value_t = 10)]
-    attempts: u8,
+    #[arg(short, long, default_value_t = DEFAULT_MAX_ATTEMPTS, value_parser = clap::value_parser!(u32).range(1..))]
+    attempts: u32,
+
+    #[arg(long, default_value_t = false)]
+    syslog: bool,
+
+    #[arg(long)]
+    log_dir: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false)]
+    quiet_servers: bool,
+
+    #[arg(long, default_value_t = false)]
+    quiet_command: bool,
+
+    #[arg(long)]
+    control_port: Option<u16>,
+
+    #[arg(long)]
+    control_socket: Option<PathBuf>,
+
+    #[arg(long, num_args = 0..=1, value_name = "FILE")]
+    profile_startup: Option<Option<PathBuf>>,
+
+    #[command(subcommand)]
+    subcommand: Option<Commands>,
 }
 
-#[derive(serde::Deserialize)]
-struct Server {
-    name: String,
-    url: String,
-    command: String,
+#[derive(Subcommand)]
+enum Commands {
+    Ctl {
+        #[arg(short, long, default_value = DEFAULT_CONTROL_SOCKET)]
+        socket: PathBuf,
+
+        #[command(subcommand)]
+        action: CtlAction,
+    },
+    Wait {
+        #[arg(long)]
+        url: Vec<String>,
+    },
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+    #[command(hide = true)]
+    ServeStatic {
+        dir: PathBuf,
+
+        #[arg(long)]
+        port: u16,
+
+        #[arg(long, default_value_t = false)]
+        spa: bool,
+    },
+    #[command(hide = true)]
+    ServeMock {
+        #[arg(long)]
+        port: u16,
+
+        #[arg(long)]
+        routes: String,
+    },
+    Bench {
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        runs: u32,
+
+        #[arg(long, default_value_t = false)]
+        with_command: bool,
+
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+
+        #[arg(long)]
+        save_baseline: Option<PathBuf>,
+    },
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+    },
 }
 
-#[derive(serde::Deserialize)]
-struct Config {
-    servers: Vec<Server>,
-    command: String,
+#[derive(Clone, ValueEnum)]
+enum ExportFormat {
+    Tmux,
+    Zellij,
 }
 
-struct ServerProcess {
-    name: String,
-    process: Child,
+#[derive(Subcommand)]
+enum HistoryAction {
+    Show { id: usize },
 }
 
-#[derive(PartialEq, Eq)]
-enum ServerStatus {
-    Waiting,
-    Running,
+#[derive(Subcommand)]
+enum CtlAction {
+    Status,
+    Snapshot,
+    Restart { name: String },
+    Stop,
 }
 
 fn run(args: Args) -> anyhow::Result<()> {
-    let config = get_config(args.config)?;
-    let server_processes = Arc::new(Mutex::new(start_servers(&config)?));
-    let mut attempts: HashMap<String, u8> = HashMap::new();
+    let started = Instant::now();
+    #[cfg(unix)]
+    let listen_sockets = systemd::listen_sockets();
+    let config = Config::load(&args.config)?;
+    let profile = args
+        .profile_startup
+        .is_some()
+        .then(|| Arc::new(StartupProfile::new(started)));
+
+    if let Some(profile) = &profile {
+        profile.record("load config", started, Instant::now());
+    }
+
+    let history = config
+        .history_file
+        .as_ref()
+        .map(|path| RunHistory::new(path, &args.config, started).map(Arc::new))
+        .transpose()?;
     let log_level = if args.verbose {
         simplelog::LevelFilter::Info
     } else {
         simplelog::LevelFilter::Warn
     };
 
-    simplelog::TermLogger::init(
-        log_level,
-        simplelog::Config::default(),
-        simplelog::TerminalMode::Mixed,
-        simplelog::ColorChoice::Auto,
-    )?;
+    init_logger(log_level, args.syslog)?;
 
-    let server_processes_clone = Arc::clone(&server_processes);
-    ctrlc::set_handler(move || {
-        let mut server_processes = server_processes_clone.lock().unwrap();
+    let mut output_settings = OutputSettings::from_config(&config);
 
-        match stop_servers(&mut server_processes) {
-            Ok(_) => info!("All servers stopped successfully"),
-            Err(e) => info!("Could not stop servers: {}", e),
-        }
+    output_settings.log_dir = args.log_dir.or(output_settings.log_dir);
+    output_settings.quiet_servers |= args.quiet_servers;
+    output_settings.quiet_command |= args.quiet_command;
+
+    let control_port = args.control_port.or(config.control_port);
+    let control_socket = args.control_socket.or(config.control_socket.clone());
+
+    #[cfg(unix)]
+    let socket_activated = !listen_sockets.is_empty();
+    #[cfg(not(unix))]
+    let socket_activated = false;
+
+    if control_port.is_some() || socket_activated {
+        output_settings.buffer_lines = output_settings.buffer_lines.max(CONTROL_LOG_LINES);
+    }
 
+    let tracer = Tracer::from_env().map(Arc::new);
+    let notifier = Arc::new(Notifier::new(&config.notifications)?);
+    let notifier_clone = Arc::clone(&notifier);
+    let mut builder = ServerRunnerBuilder::new(config)
+        .output_settings(output_settings)
+        .on_event(log_event)
+        .on_event(move |event| notifier_clone.handle(event));
+
+    if let Some(tracer) = &tracer {
+        let tracer = Arc::clone(tracer);
+
+        builder = builder.on_event(move |event| tracer.handle(event));
+    }
+
+    if let Some(profile) = &profile {
+        let profile = Arc::clone(profile);
+
+        builder = builder.on_event(move |event| profile.handle(event));
+    }
+
+    if let Some(history) = &history {
+        let history = Arc::clone(history);
+
+        builder = builder.on_event(move |event| history.handle(event));
+    }
+
+    let starting = Instant::now();
+    let server_manager = builder.start()?;
+
+    if let Some(profile) = &profile {
+        profile.record("start servers", starting, Instant::now());
+    }
+
+    let teardown = Teardown {
+        server_manager: server_manager.clone(),
+        control_socket: control_socket.clone(),
+        notifier,
+        tracer,
+        history,
+        once: Arc::new(Once::new()),
+    };
+    let _teardown_guard = TeardownGuard(teardown.clone());
+
+    let teardown_clone = teardown.clone();
+    ctrlc::set_handler(move || {
+        teardown_clone.run();
         std::process::exit(0);
     })?;
 
-    loop {
-        let mut ready = true;
+    if let Some(port) = control_port {
+        let teardown_clone = teardown.clone();
 
-        for server in &config.servers {
-            match check_server(&server, &mut attempts, args.attempts) {
-                Ok(result) => {
-                    if result == ServerStatus::Waiting {
-                        ready = false;
-                    }
-                }
-                Err(e) => {
-                    let mut server_processes = server_processes.lock().unwrap();
+        control::serve(port, server_manager.clone(), move || {
+            teardown_clone.run();
+            std::process::exit(0);
+        })?;
+    }
 
-                    match stop_servers(&mut server_processes) {
-                        Ok(_) => info!("All servers stopped successfully"),
-                        Err(e) => info!("Could not stop servers: {}", e),
-                    }
+    if let Some(path) = &control_socket {
+        #[cfg(unix)]
+        {
+            let teardown_clone = teardown.clone();
 
-                    return Err(e);
-                }
+            control::serve_socket(path, server_manager.clone(), move || {
+                teardown_clone.run();
+                std::process::exit(0);
+            })?;
+        }
+
+        #[cfg(not(unix))]
+        anyhow::bail!(
+            "Control socket {} is only supported on Unix",
+            path.display()
+        );
+    }
+
+    #[cfg(unix)]
+    for socket in listen_sockets {
+        let teardown_clone = teardown.clone();
+        let shutdown = move || {
+            teardown_clone.run();
+            std::process::exit(0);
+        };
+
+        match socket {
+            ListenSocket::Tcp(listener) => {
+                control::serve_listener(listener, server_manager.clone(), shutdown)?
+            }
+            ListenSocket::Unix(listener) => {
+                control::serve_unix_listener(listener, server_manager.clone(), shutdown)
             }
         }
+    }
 
-        if ready {
-            let mut process = run_command(&config.command)
-                .context(format!("Could not start process {}", &config.command))?;
+    if let Some(port) = server_manager.config().proxy_port {
+        proxy::serve(port, server_manager.config())?;
+    }
 
-            info!("Running command {}", &config.command);
+    #[cfg(unix)]
+    dump_snapshots_on_signal(server_manager.clone())?;
 
-            process.wait()?;
+    let waiting = Instant::now();
 
-            info!("Command {} finished successfully", &config.command);
+    server_manager.wait_for_servers(args.attempts)?;
 
-            break;
-        }
+    if let Some(profile) = &profile {
+        profile.record("wait for servers", waiting, Instant::now());
 
-        thread::sleep(Duration::from_secs(1));
+        match args.profile_startup.flatten() {
+            Some(path) => profile.write_chrome_trace(&path)?,
+            None => profile.print(),
+        }
     }
 
-    let mut server_processes = server_processes.lock().unwrap();
+    #[cfg(unix)]
+    {
+        if let Err(e) = systemd::notify("READY=1\nSTATUS=All servers are ready") {
+            warn!("Could not notify systemd: {}", e);
+        }
 
-    match stop_servers(&mut server_processes) {
-        Ok(_) => info!("All servers stopped successfully"),
-        Err(e) => info!("Could not stop servers: {}", e),
+        systemd::start_watchdog(server_manager.clone());
     }
 
+    server_manager.run_stages(args.attempts)?;
+
     Ok(())
 }
 
-fn get_config(filename: String) -> anyhow::Result<Config> {
-    let cwd = env::current_dir()?;
-    let tmp_path = cwd.join(&filename);
-    let config_file_path = tmp_path.to_str().context(format!(
-        "Could not create String from Path {}",
-        tmp_path.display()
-    ))?;
+fn log_event(event: &Event) {
+    match event {
+        Event::CheckAttempt {
+            name,
+            url: Some(url),
+            attempt,
+        } => info!(
+            "Checking server {} on url {}, attempt {}",
+            name, url, attempt
+        ),
+        Event::CheckAttempt {
+            name,
+            url: None,
+            attempt,
+        } => info!("Checking server {}, attempt {}", name, attempt),
+        Event::ServerReady { name } => info!("Server {} is ready", name),
+        Event::CommandExited {
+            command, status, ..
+        } => {
+            info!("Command {} finished with {}", command, status)
+        }
+        _ => {}
+    }
+}
 
-    info!("Loading config file {}", config_file_path);
+#[derive(Clone)]
+struct Teardown {
+    server_manager: ServerManager,
+    control_socket: Option<PathBuf>,
+    notifier: Arc<Notifier>,
+    tracer: Option<Arc<Tracer>>,
+    history: Option<Arc<RunHistory>>,
+    once: Arc<Once>,
+}
 
-    let settings = config::Config::builder()
-        .add_source(config::File::new(
-            config_file_path,
-            config::FileFormat::Yaml,
-        ))
-        .build()
-        .context(format!("Could not find config file {}", &filename))?;
+impl Teardown {
+    fn run(&self) {
+        self.once.call_once(|| {
+            #[cfg(unix)]
+            systemd::notify("STOPPING=1").ok();
 
-    let config = settings
-        .try_deserialize::<Config>()
-        .context(format!("Could not parse config file {}", &filename))?;
+            match self.server_manager.stop() {
+                Ok(_) => info!("All servers stopped successfully"),
+                Err(e) => info!("Could not stop servers: {}", e),
+            }
 
-    Ok(config)
-}
+            if let Some(path) = &self.control_socket {
+                std::fs::remove_file(path).ok();
+            }
 
-fn start_servers(config: &Config) -> anyhow::Result<Vec<ServerProcess>> {
-    let mut server_processes = Vec::with_capacity(config.servers.len());
+            self.notifier.finish();
 
-    for s in &config.servers {
-        info!("Starting server {}", s.name);
+            if let Some(tracer) = &self.tracer {
+                if let Err(e) = tracer.export() {
+                    warn!("{:#}", e);
+                }
+            }
 
-        let process = run_command(&s.command)?;
+            if let Some(history) = &self.history {
+                if let Err(e) = history.save() {
+                    warn!("{:#}", e);
+                }
+            }
+        });
+    }
+}
 
-        let server_process = ServerProcess {
-            name: s.name.to_string(),
-            process,
-        };
+struct TeardownGuard(Teardown);
 
-        server_processes.push(server_process);
+impl Drop for TeardownGuard {
+    fn drop(&mut self) {
+        self.0.run();
     }
-
-    Ok(server_processes)
 }
 
-fn stop_servers(server_processes: &mut Vec<ServerProcess>) -> anyhow::Result<()> {
-    for p in server_processes.iter_mut() {
-        info!("Stopping server {}", p.name);
+#[cfg(unix)]
+fn dump_snapshots_on_signal(server_manager: ServerManager) -> anyhow::Result<()> {
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGUSR1])
+        .context("Could not register SIGUSR1 handler")?;
 
-        p.process
-            .kill()
-            .context(format!("Failed to stop process {}", p.name))?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            let snapshot = match serde_json::to_string_pretty(&server_manager.snapshot()) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    warn!("Could not serialize snapshot: {}", e);
+                    continue;
+                }
+            };
+
+            match &server_manager.config().snapshot_file {
+                Some(path) => {
+                    if let Err(e) = std::fs::write(path, snapshot) {
+                        warn!("Could not write snapshot to {}: {}", path.display(), e);
+                    }
+                }
+                None => eprintln!("{}", snapshot),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn ctl(socket: &Path, action: CtlAction) -> anyhow::Result<()> {
+    match action {
+        CtlAction::Status => {
+            let states: Vec<server_runner::ServerState> =
+                serde_json::from_str(&control::send(socket, "status")?)?;
+
+            for state in states {
+                let status = if !state.running {
+                    "exited"
+                } else if state.ready {
+                    "ready"
+                } else {
+                    "waiting"
+                };
+
+                match state.url {
+                    Some(url) => {
+                        println!("{}: {} (pid {}, {})", state.name, status, state.pid, url)
+                    }
+                    None => println!("{}: {} (pid {})", state.name, status, state.pid),
+                }
+            }
+        }
+        CtlAction::Restart { name } => {
+            println!("{}", control::send(socket, &format!("restart {}", name))?)
+        }
+        CtlAction::Snapshot => println!("{}", control::send(socket, "snapshot")?),
+        CtlAction::Stop => println!("{}", control::send(socket, "stop")?),
     }
 
     Ok(())
 }
 
-fn run_command(command: &String) -> anyhow::Result<Child> {
-    let command_parts: Vec<&str> = command.split(" ").collect();
-    let mut cmd = Command::new(command_parts[0]);
+#[cfg(not(unix))]
+fn ctl(_socket: &Path, _action: CtlAction) -> anyhow::Result<()> {
+    anyhow::bail!("The control socket is only supported on Unix")
+}
 
-    for i in 1..command_parts.len() {
-        cmd.arg(command_parts[i]);
-    }
+fn wait(config_file: &str, urls: Vec<String>, attempts: u32, verbose: bool) -> anyhow::Result<()> {
+    let log_level = if verbose {
+        simplelog::LevelFilter::Info
+    } else {
+        simplelog::LevelFilter::Warn
+    };
 
-    #[cfg(windows)]
-    {
-        cmd.creation_flags(0x08000000);
-    }
+    init_logger(log_level, false)?;
 
-    let child = cmd
-        .spawn()
-        .context(format!("Could not start procces '{}'", &command))?;
+    let config = if urls.is_empty() {
+        Config::load_external(config_file)?
+    } else {
+        Config::from_urls(&urls)?
+    };
 
-    Ok(child)
+    server_runner::wait_for_external_servers(config, attempts)
 }
 
-fn check_server(
-    server: &Server,
-    server_attempts: &mut HashMap<String, u8>,
-    max_attempts: u8,
-) -> anyhow::Result<ServerStatus> {
-    let server_name = &server.name;
+fn run_bench(
+    config_file: &str,
+    attempts: u32,
+    runs: u32,
+    with_command: bool,
+    baseline: Option<PathBuf>,
+    save_baseline: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    init_logger(simplelog::LevelFilter::Warn, false)?;
+
+    let baseline = baseline
+        .map(|path| bench::load_baseline(&path))
+        .transpose()?;
+    let bench = Arc::new(Bench::default());
+    let current: Arc<Mutex<Option<ServerManager>>> = Arc::new(Mutex::new(None));
+    let current_clone = Arc::clone(&current);
 
-    if server_attempts.contains_key(server_name) {
-        *server_attempts.get_mut(server_name).unwrap() += 1;
-    } else {
-        server_attempts.insert(server_name.to_owned(), 1);
+    ctrlc::set_handler(move || {
+        if let Some(server_manager) = current_clone.lock().unwrap().take() {
+            server_manager.stop().ok();
+        }
+
+        std::process::exit(0);
+    })?;
+
+    for run in 1..=runs {
+        let config = Config::load(config_file)?;
+        let mut output_settings = OutputSettings::from_config(&config);
+
+        output_settings.quiet_servers = true;
+        bench.start_run();
+
+        let bench_clone = Arc::clone(&bench);
+        let server_manager = ServerRunnerBuilder::new(config)
+            .output_settings(output_settings)
+            .on_event(move |event| bench_clone.handle(event))
+            .start()?;
+
+        *current.lock().unwrap() = Some(server_manager.clone());
+
+        let mut result = server_manager.wait_for_servers(attempts);
+
+        if with_command && result.is_ok() {
+            result = server_manager.run_stages(attempts);
+        }
+
+        current.lock().unwrap().take();
+        server_manager.stop()?;
+        result.context(format!("Benchmark run {} failed", run))?;
+
+        eprintln!("Finished run {} of {}", run, runs);
     }
 
-    let attempts = *server_attempts.get(server_name).unwrap();
+    let stats = bench.stats();
 
-    if attempts == max_attempts {
-        bail!(
-            "Could not connect to server {} after {} attempts",
-            server_name,
-            attempts
-        );
+    bench::print(&stats, baseline.as_ref());
+
+    if let Some(path) = save_baseline {
+        bench::save_baseline(&path, &stats)?;
+    }
+
+    Ok(())
+}
+
+fn serve_mock(port: u16, routes: &str) -> anyhow::Result<()> {
+    let routes = serde_json::from_str(routes).context("Could not parse mock routes")?;
+
+    mock::serve(port, routes)?.wait();
+
+    Ok(())
+}
+
+fn export(config_file: &str, format: ExportFormat) -> anyhow::Result<()> {
+    let config = Config::load(config_file)?;
+    let panes = layout::panes(&config, config_file)?;
+
+    match format {
+        ExportFormat::Tmux => print!("{}", layout::tmux(&panes)),
+        ExportFormat::Zellij => print!("{}", layout::zellij(&panes)),
     }
 
-    info!(
-        "Checking server {} on url {}, attempt {}, waiting one second ...",
-        server_name, &server.url, attempts
-    );
-
-    let result = match reqwest::blocking::get(&server.url) {
-        Ok(response) => response.status(),
-        Err(error) => {
-            if error.is_connect() {
-                return Ok(ServerStatus::Waiting);
-            } else {
-                bail!(
-                    "Could not connect to server {} on url {}",
-                    &server_name,
-                    &server.url
+    Ok(())
+}
+
+fn show_history(config_file: &str, action: Option<HistoryAction>) -> anyhow::Result<()> {
+    let config = Config::load(config_file)?;
+    let path = config.history_file.context(format!(
+        "No history_file is set in config file {}",
+        config_file
+    ))?;
+    let runs = history::load(&path)?;
+
+    match action {
+        None => {
+            for run in runs {
+                let result = match (&run.error, run.exit_code) {
+                    (Some(_), _) => "failed".to_string(),
+                    (None, Some(code)) => format!("exit {}", code),
+                    (None, None) => "interrupted".to_string(),
+                };
+
+                println!(
+                    "{:>4}  {}  {:>8}ms  {}  {}",
+                    run.id, run.started, run.duration_ms, run.config_hash, result
                 );
             }
         }
-    };
+        Some(HistoryAction::Show { id }) => {
+            let run = runs.into_iter().find(|run| run.id == id).context(format!(
+                "Unknown run {} in history file {}",
+                id,
+                path.display()
+            ))?;
+
+            println!("Run {} started {}", run.id, run.started);
+            println!("Config hash: {}", run.config_hash);
+            println!("Duration: {}ms", run.duration_ms);
+
+            for server in run.servers {
+                match server.ready_after_ms {
+                    Some(ms) => println!(
+                        "{}: ready after {}ms, {} attempts",
+                        server.name, ms, server.attempts
+                    ),
+                    None => println!("{}: not ready, {} attempts", server.name, server.attempts),
+                }
+            }
 
-    return if result.is_success() {
-        Ok(ServerStatus::Running)
-    } else {
-        Ok(ServerStatus::Waiting)
-    };
+            if let Some(code) = run.exit_code {
+                println!("Command exit code: {}", code);
+            }
+
+            if let Some(error) = run.error {
+                println!("Error: {}", error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn init_logger(log_level: simplelog::LevelFilter, syslog: bool) -> anyhow::Result<()> {
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut loggers: Vec<Box<dyn simplelog::SharedLogger>> = vec![simplelog::TermLogger::new(
+        log_level,
+        simplelog::Config::default(),
+        simplelog::TerminalMode::Mixed,
+        simplelog::ColorChoice::Auto,
+    )];
+
+    if syslog {
+        #[cfg(unix)]
+        {
+            let writer = server_runner::Syslog::connect("server-runner", std::process::id())
+                .context("Could not connect to syslog")?;
+            let config = simplelog::ConfigBuilder::new()
+                .set_time_level(simplelog::LevelFilter::Off)
+                .build();
+
+            loggers.push(simplelog::WriteLogger::new(log_level, config, writer));
+        }
+
+        #[cfg(not(unix))]
+        anyhow::bail!("Syslog is only supported on Unix");
+    }
+
+    simplelog::CombinedLogger::init(loggers)?;
+
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    run(args)
+    match args.subcommand {
+        Some(Commands::Ctl { socket, action }) => ctl(&socket, action),
+        Some(Commands::ServeMock { port, routes }) => serve_mock(port, &routes),
+        Some(Commands::ServeStatic { dir, port, spa }) => {
+            static_files::serve(&dir, port, spa)?.wait();
+            Ok(())
+        }
+        Some(Commands::Wait { url }) => wait(&args.config, url, args.attempts, args.verbose),
+        Some(Commands::History { action }) => show_history(&args.config, action),
+        Some(Commands::Bench {
+            runs,
+            with_command,
+            baseline,
+            save_baseline,
+        }) => run_bench(
+            &args.config,
+            args.attempts,
+            runs,
+            with_command,
+            baseline,
+            save_baseline,
+        ),
+        Some(Commands::Export { format }) => export(&args.config, format),
+        None => run(args),
+    }
 }
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Context};
+use log::info;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request, Response};
+
+use crate::config::Server;
+use crate::in_process::InProcessServer;
+use crate::static_files;
+
+const HTTP_METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct MockRoute {
+    pub path: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default, with = "humantime_serde")]
+    pub latency: Option<Duration>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+fn openapi_routes(path: &Path) -> anyhow::Result<Vec<MockRoute>> {
+    let spec: Value = config::Config::builder()
+        .add_source(config::File::from(path))
+        .build()
+        .and_then(|spec| spec.try_deserialize())
+        .context(format!("Could not read OpenAPI file {}", path.display()))?;
+    let mut routes = Vec::new();
+
+    for (path, operations) in spec["paths"].as_object().into_iter().flatten() {
+        for (method, operation) in operations.as_object().into_iter().flatten() {
+            if !HTTP_METHODS.contains(&method.as_str()) {
+                continue;
+            }
+
+            let responses = operation["responses"].as_object();
+            let (status, response) = responses
+                .and_then(|responses| {
+                    responses
+                        .iter()
+                        .find(|(status, _)| status.starts_with('2'))
+                        .or_else(|| responses.iter().next())
+                })
+                .map(|(status, response)| (status.parse().unwrap_or(200), response))
+                .unwrap_or((200, &Value::Null));
+            let body = match &response["content"]["application/json"]["example"] {
+                Value::Null => String::new(),
+                example => example.to_string(),
+            };
+
+            routes.push(MockRoute {
+                path: path.to_string(),
+                method: method.to_uppercase(),
+                status,
+                body,
+                latency: None,
+            });
+        }
+    }
+
+    Ok(routes)
+}
+
+fn routes(server: &Server) -> anyhow::Result<Vec<MockRoute>> {
+    let mut routes = server.routes.clone();
+
+    if let Some(openapi) = &server.openapi {
+        routes.extend(openapi_routes(openapi)?);
+    }
+
+    Ok(routes)
+}
+
+pub fn run_command(server: &Server) -> anyhow::Result<Command> {
+    let mut cmd = Command::new(static_files::program());
+
+    cmd.args([
+        "serve-mock",
+        "--port",
+        &static_files::port(server)?.to_string(),
+        "--routes",
+    ])
+    .arg(serde_json::to_string(&routes(server)?)?);
+
+    Ok(cmd)
+}
+
+pub fn start(server: &Server) -> anyhow::Result<InProcessServer> {
+    serve(static_files::port(server)?, routes(server)?)
+}
+
+fn matches(route: &str, path: &str) -> bool {
+    let route: Vec<&str> = route.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    route.len() == path.len()
+        && route
+            .iter()
+            .zip(&path)
+            .all(|(route, path)| route == path || (route.starts_with('{') && route.ends_with('}')))
+}
+
+fn respond(request: Request, routes: &[MockRoute]) {
+    let path = request.url().split('?').next().unwrap_or_default();
+    let route = routes.iter().find(|route| {
+        route.method.eq_ignore_ascii_case(request.method().as_str()) && matches(&route.path, path)
+    });
+    let Some(route) = route else {
+        request
+            .respond(Response::from_string("Not found").with_status_code(404))
+            .ok();
+        return;
+    };
+
+    if let Some(latency) = route.latency {
+        thread::sleep(latency);
+    }
+
+    let content_type = if route.body.starts_with(['{', '[']) {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    let header =
+        Header::from_bytes("Content-Type", content_type).expect("Content type header is valid");
+
+    request
+        .respond(
+            Response::from_string(route.body.as_str())
+                .with_status_code(route.status)
+                .with_header(header),
+        )
+        .ok();
+}
+
+pub fn serve(port: u16, routes: Vec<MockRoute>) -> anyhow::Result<InProcessServer> {
+    let routes = Arc::new(routes);
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("Could not start mock server on port {}: {}", port, e))?;
+
+    info!("Mock server listening on http://127.0.0.1:{}", port);
+
+    Ok(InProcessServer::start(server, move |request| {
+        let routes = Arc::clone(&routes);
+
+        thread::spawn(move || respond(request, &routes));
+    }))
+}
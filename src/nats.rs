@@ -0,0 +1,162 @@
+use anyhow::Context;
+use log::warn;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::events::Event;
+
+const DEFAULT_PORT: u16 = 4222;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Credentials {
+    User { user: String, pass: String },
+    Token(String),
+}
+
+fn parse_url(url: &str) -> (String, Option<Credentials>) {
+    let url = url.trim_start_matches("nats://").trim_end_matches('/');
+    let (credentials, address) = match url.rsplit_once('@') {
+        Some((userinfo, address)) => {
+            let credentials = match userinfo.split_once(':') {
+                Some((user, pass)) => Credentials::User {
+                    user: user.to_string(),
+                    pass: pass.to_string(),
+                },
+                None => Credentials::Token(userinfo.to_string()),
+            };
+
+            (Some(credentials), address)
+        }
+        None => (None, url),
+    };
+    let has_port = address
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+
+    if has_port {
+        (address.to_string(), credentials)
+    } else {
+        (format!("{}:{}", address, DEFAULT_PORT), credentials)
+    }
+}
+
+fn connect_message(credentials: Option<&Credentials>) -> String {
+    let mut options = json!({ "verbose": false, "pedantic": false });
+
+    match credentials {
+        Some(Credentials::User { user, pass }) => {
+            options["user"] = json!(user);
+            options["pass"] = json!(pass);
+        }
+        Some(Credentials::Token(token)) => options["auth_token"] = json!(token),
+        None => {}
+    }
+
+    format!("CONNECT {}\r\n", options)
+}
+
+pub struct Nats {
+    stream: Mutex<TcpStream>,
+    subject: String,
+}
+
+impl Nats {
+    pub fn connect(url: &str, subject: &str) -> anyhow::Result<Nats> {
+        let (address, credentials) = parse_url(url);
+        let stream = address
+            .to_socket_addrs()
+            .ok()
+            .into_iter()
+            .flatten()
+            .find_map(|address| TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).ok())
+            .context(format!("Could not connect to NATS nats://{}", address))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut info = String::new();
+
+        reader.read_line(&mut info)?;
+        (&stream).write_all(connect_message(credentials.as_ref()).as_bytes())?;
+
+        let mut writer = stream.try_clone()?;
+
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if line.starts_with("PING") && writer.write_all(b"PONG\r\n").is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Nats {
+            stream: Mutex::new(stream),
+            subject: subject.to_string(),
+        })
+    }
+
+    pub fn publish(&self, event: &Event) {
+        let payload = match serde_json::to_value(event) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let subject = format!(
+            "{}.{}",
+            self.subject,
+            payload["event"].as_str().unwrap_or_default()
+        );
+        let payload = payload.to_string();
+        let message = format!("PUB {} {}\r\n{}\r\n", subject, payload.len(), payload);
+
+        if let Err(e) = self.stream.lock().unwrap().write_all(message.as_bytes()) {
+            warn!("Could not publish event to NATS: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn adds_default_port() {
+        assert_eq!(parse_url("nats://localhost").0, "localhost:4222");
+        assert_eq!(parse_url("nats://localhost:4333/").0, "localhost:4333");
+        assert_eq!(parse_url("localhost").0, "localhost:4222");
+    }
+
+    #[test]
+    fn sends_credentials_of_url() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("nats://user:p:ss@{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut connect = String::new();
+
+            (&stream).write_all(b"INFO {}\r\n").unwrap();
+            reader.read_line(&mut connect).unwrap();
+            connect
+        });
+
+        Nats::connect(&url, "server-runner").unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            "CONNECT {\"pass\":\"p:ss\",\"pedantic\":false,\"user\":\"user\",\"verbose\":false}\r\n"
+        );
+    }
+
+    #[test]
+    fn sends_token_of_url() {
+        let (address, credentials) = parse_url("nats://s3cr3t@localhost");
+
+        assert_eq!(address, "localhost:4222");
+        assert_eq!(
+            connect_message(credentials.as_ref()),
+            "CONNECT {\"auth_token\":\"s3cr3t\",\"pedantic\":false,\"verbose\":false}\r\n"
+        );
+    }
+}
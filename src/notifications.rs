@@ -0,0 +1,304 @@
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::events::Event;
+use crate::nats::Nats;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_NATS_SUBJECT: &str = "server-runner";
+const DESKTOP_EVENTS: [NotificationEvent; 3] = [
+    NotificationEvent::Ready,
+    NotificationEvent::ReadinessFailed,
+    NotificationEvent::CommandFinished,
+];
+const CHAT_EVENTS: [NotificationEvent; 3] = [
+    NotificationEvent::ReadinessFailed,
+    NotificationEvent::ServerCrashed,
+    NotificationEvent::CommandFinished,
+];
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    Ready,
+    ReadinessFailed,
+    ServerCrashed,
+    CommandFinished,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct Notifications {
+    pub webhook: Option<Webhook>,
+    pub slack: Option<Webhook>,
+    pub discord: Option<Webhook>,
+    #[serde(default)]
+    pub desktop: bool,
+    pub nats: Option<NatsSettings>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct NatsSettings {
+    pub url: String,
+    #[serde(default = "default_nats_subject")]
+    pub subject: String,
+}
+
+fn default_nats_subject() -> String {
+    DEFAULT_NATS_SUBJECT.to_string()
+}
+
+#[derive(serde::Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    pub events: Option<Vec<NotificationEvent>>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Slack,
+    Discord,
+}
+
+struct Target {
+    url: String,
+    events: Vec<NotificationEvent>,
+    format: Format,
+}
+
+#[cfg(feature = "desktop-notifications")]
+fn show_desktop_notification(message: &str) {
+    let result = notify_rust::Notification::new()
+        .summary("Server Runner")
+        .body(message)
+        .show();
+
+    if let Err(e) = result {
+        warn!("Could not show desktop notification: {}", e);
+    }
+}
+
+#[cfg(not(feature = "desktop-notifications"))]
+fn show_desktop_notification(_message: &str) {}
+
+struct Delivery {
+    url: String,
+    body: Value,
+}
+
+fn deliver(client: &reqwest::blocking::Client, delivery: Delivery) {
+    let result = client
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .body(delivery.body.to_string())
+        .send()
+        .and_then(|response| response.error_for_status());
+
+    match result {
+        Ok(_) => info!("Sent notification to {}", delivery.url),
+        Err(e) => warn!("Could not send notification to {}: {}", delivery.url, e),
+    }
+}
+
+pub struct Notifier {
+    targets: Vec<Target>,
+    desktop: bool,
+    nats: Option<Nats>,
+    deliveries: Mutex<Option<Sender<Delivery>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Notifier {
+    pub fn new(notifications: &Notifications) -> anyhow::Result<Notifier> {
+        let targets: Vec<Target> = [
+            (&notifications.webhook, Format::Json),
+            (&notifications.slack, Format::Slack),
+            (&notifications.discord, Format::Discord),
+        ]
+        .into_iter()
+        .filter_map(|(webhook, format)| {
+            webhook.as_ref().map(|webhook| Target {
+                url: webhook.url.clone(),
+                events: webhook.events.clone().unwrap_or_else(|| match format {
+                    Format::Json => vec![
+                        NotificationEvent::Ready,
+                        NotificationEvent::ReadinessFailed,
+                        NotificationEvent::ServerCrashed,
+                        NotificationEvent::CommandFinished,
+                    ],
+                    Format::Slack | Format::Discord => CHAT_EVENTS.to_vec(),
+                }),
+                format,
+            })
+        })
+        .collect();
+
+        #[cfg(not(feature = "desktop-notifications"))]
+        if notifications.desktop {
+            warn!("Desktop notifications are not included in this build of Server Runner");
+        }
+
+        let nats = notifications.nats.as_ref().and_then(|settings| {
+            Nats::connect(&settings.url, &settings.subject)
+                .inspect_err(|e| warn!("{:#}, events are not published", e))
+                .ok()
+        });
+
+        // Webhooks are posted from a single worker thread so a slow receiver never holds up the
+        // event that triggered the notification.
+        let (deliveries, worker) = if targets.is_empty() {
+            (None, None)
+        } else {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()?;
+            let (sender, receiver) = mpsc::channel();
+            let worker = thread::spawn(move || {
+                for delivery in receiver {
+                    deliver(&client, delivery);
+                }
+            });
+
+            (Some(sender), Some(worker))
+        };
+
+        Ok(Notifier {
+            targets,
+            desktop: notifications.desktop,
+            nats,
+            deliveries: Mutex::new(deliveries),
+            worker: Mutex::new(worker),
+        })
+    }
+
+    /// Waits until all queued webhooks are sent. Events handled afterwards are not posted.
+    pub fn finish(&self) {
+        self.deliveries.lock().unwrap().take();
+
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            worker.join().ok();
+        }
+    }
+
+    pub fn handle(&self, event: &Event) {
+        if let Some(nats) = &self.nats {
+            nats.publish(event);
+        }
+
+        let (kind, payload, message) = match event {
+            Event::ServersReady => (
+                NotificationEvent::Ready,
+                json!({ "event": "ready" }),
+                "All servers are ready".to_string(),
+            ),
+            Event::ReadinessFailed { name, error } => (
+                NotificationEvent::ReadinessFailed,
+                json!({
+                    "event": "readiness_failed",
+                    "server": name,
+                    "error": error,
+                }),
+                format!("Server {} did not become ready: {}", name, error),
+            ),
+            Event::ServerCrashed { name, status } => (
+                NotificationEvent::ServerCrashed,
+                json!({
+                    "event": "server_crashed",
+                    "server": name,
+                    "exit_code": status.code(),
+                }),
+                format!("Server {} exited with {}", name, status),
+            ),
+            Event::CommandExited {
+                command, status, ..
+            } => (
+                NotificationEvent::CommandFinished,
+                json!({
+                    "event": "command_finished",
+                    "command": command,
+                    "exit_code": status.code(),
+                }),
+                format!("Command `{}` finished with {}", command, status),
+            ),
+            _ => return,
+        };
+
+        if self.desktop && DESKTOP_EVENTS.contains(&kind) {
+            show_desktop_notification(&message);
+        }
+
+        let deliveries = self.deliveries.lock().unwrap();
+        let Some(deliveries) = deliveries.as_ref() else {
+            return;
+        };
+
+        for target in self.targets.iter().filter(|t| t.events.contains(&kind)) {
+            let body: Value = match target.format {
+                Format::Json => payload.clone(),
+                Format::Slack => json!({ "text": format!("Server Runner: {}", message) }),
+                Format::Discord => json!({ "content": format!("Server Runner: {}", message) }),
+            };
+
+            deliveries
+                .send(Delivery {
+                    url: target.url.clone(),
+                    body,
+                })
+                .ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posts_webhooks_in_the_background() {
+        let receiver = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", receiver.server_addr());
+        let notifier = Notifier::new(&Notifications {
+            webhook: Some(Webhook {
+                url: format!("{}/webhook", url),
+                events: None,
+            }),
+            slack: Some(Webhook {
+                url: format!("{}/slack", url),
+                events: Some(vec![NotificationEvent::Ready]),
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        notifier.handle(&Event::ServersReady);
+
+        let mut bodies = Vec::new();
+
+        for _ in 0..2 {
+            let mut request = receiver.recv().unwrap();
+            let mut body = String::new();
+
+            request.as_reader().read_to_string(&mut body).unwrap();
+            bodies.push((request.url().to_string(), body));
+            request.respond(tiny_http::Response::empty(204)).unwrap();
+        }
+
+        notifier.finish();
+        bodies.sort();
+
+        assert_eq!(
+            bodies,
+            vec![
+                (
+                    "/slack".to_string(),
+                    r#"{"text":"Server Runner: All servers are ready"}"#.to_string()
+                ),
+                ("/webhook".to_string(), r#"{"event":"ready"}"#.to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::Server;
+
+fn package_manager(package_dir: &Path) -> &'static str {
+    if package_dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if package_dir.join("yarn.lock").exists() {
+        "yarn"
+    } else if package_dir.join("bun.lockb").exists() || package_dir.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+pub fn run_command(server: &Server) -> (Command, String) {
+    let package_dir = env::current_dir()
+        .unwrap_or_default()
+        .join(server.package_dir.clone().unwrap_or(PathBuf::from(".")));
+    let script = server.script.as_deref().unwrap_or_default();
+    let package_manager = package_manager(&package_dir);
+    let mut paths = vec![package_dir.join("node_modules").join(".bin")];
+
+    if let Some(path) = env::var_os("PATH") {
+        paths.extend(env::split_paths(&path));
+    }
+
+    #[cfg(windows)]
+    let mut cmd = Command::new(format!("{}.cmd", package_manager));
+    #[cfg(not(windows))]
+    let mut cmd = Command::new(package_manager);
+
+    cmd.args(["run", script]).current_dir(&package_dir);
+
+    if let Ok(path) = env::join_paths(paths) {
+        cmd.env("PATH", path);
+    }
+
+    // The script runs in its own process group so it can be killed with its children, but a
+    // background group is stopped as soon as it reads from the terminal.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        cmd.process_group(0).stdin(Stdio::null());
+    }
+
+    (cmd, format!("{} run {}", package_manager, script))
+}
@@ -0,0 +1,1095 @@
+use anyhow::Context;
+use log::warn;
+use regex::RegexSet;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+
+use crate::config::{Config, Server, ServerType};
+use crate::ring_buffer::RingBuffer;
+#[cfg(unix)]
+use crate::syslog::{self, Syslog};
+use crate::{cargo, console, docker, npm, ssh};
+
+const DEFAULT_LOG_PATH: &str = "{name}.log";
+const DEFAULT_MAX_FILES: u32 = 5;
+const DEFAULT_PREFIX: &str = "[{name}]";
+const COMMAND_LOG_NAME: &str = "command";
+const PARTIAL_LINE_TIMEOUT: Duration = Duration::from_millis(200);
+const PREFIX_COLORS: [u8; 10] = [36, 33, 35, 32, 34, 96, 93, 95, 92, 94];
+const STDERR_COLOR: u8 = 31;
+
+static NEXT_PREFIX_COLOR: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(serde::Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Inherit,
+    Prefix,
+    File,
+    Json,
+    Syslog,
+    Tee,
+}
+
+#[derive(serde::Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    Rfc3339,
+    Relative,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct Output {
+    #[serde(default)]
+    pub mode: OutputMode,
+    pub prefix: Option<String>,
+    pub stderr_marker: Option<String>,
+    pub path: Option<String>,
+    pub max_size: Option<u64>,
+    pub max_files: Option<u32>,
+    pub timestamps: Option<TimestampFormat>,
+    pub filter: Option<Filter>,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct Filter {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+struct LineFilter {
+    include: RegexSet,
+    exclude: RegexSet,
+}
+
+impl LineFilter {
+    fn new(filter: &Filter) -> anyhow::Result<LineFilter> {
+        Ok(LineFilter {
+            include: RegexSet::new(&filter.include)?,
+            exclude: RegexSet::new(&filter.exclude)?,
+        })
+    }
+
+    fn matches(&self, line: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        (self.include.is_empty() || self.include.is_match(line)) && !self.exclude.is_match(line)
+    }
+}
+
+pub struct OutputSettings {
+    pub timestamps: Option<TimestampFormat>,
+    pub log_dir: Option<PathBuf>,
+    pub tail_lines: usize,
+    pub buffer_lines: usize,
+    pub quiet_servers: bool,
+    pub quiet_command: bool,
+    pub started: Instant,
+}
+
+impl OutputSettings {
+    pub fn from_config(config: &Config) -> OutputSettings {
+        OutputSettings {
+            timestamps: config.timestamps,
+            log_dir: config.log_dir.clone(),
+            tail_lines: config.failure_output_lines,
+            buffer_lines: config.failure_output_lines,
+            quiet_servers: config.quiet_servers,
+            quiet_command: config.quiet_command,
+            started: Instant::now(),
+        }
+    }
+}
+
+type CloseHandler = Box<dyn FnOnce() + Send>;
+
+#[derive(Clone)]
+pub struct OutputTail {
+    lines: Arc<Mutex<RingBuffer<String>>>,
+    open_streams: Arc<AtomicUsize>,
+    threads: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    on_close: Arc<Mutex<Option<CloseHandler>>>,
+}
+
+impl OutputTail {
+    fn new(capacity: usize) -> OutputTail {
+        OutputTail {
+            lines: Arc::new(Mutex::new(RingBuffer::new(capacity))),
+            open_streams: Arc::new(AtomicUsize::new(0)),
+            threads: Arc::new(Mutex::new(Vec::new())),
+            on_close: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn push(&self, line: &[u8]) {
+        self.lines.lock().unwrap().push(
+            String::from_utf8_lossy(line)
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        );
+    }
+
+    pub fn wait_for_close(&self, timeout: Duration) {
+        let started = Instant::now();
+
+        while self.open_streams.load(Ordering::SeqCst) > 0 && started.elapsed() < timeout {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    pub fn on_close(&self, handler: impl FnOnce() + Send + 'static) {
+        let mut on_close = self.on_close.lock().unwrap();
+
+        if self.open_streams.load(Ordering::SeqCst) == 0 {
+            drop(on_close);
+            handler();
+        } else {
+            *on_close = Some(Box::new(handler));
+        }
+    }
+
+    pub fn join(&self, timeout: Duration) -> bool {
+        self.wait_for_close(timeout);
+
+        if self.open_streams.load(Ordering::SeqCst) > 0 {
+            return false;
+        }
+
+        for thread in self.threads.lock().unwrap().drain(..) {
+            thread.join().ok();
+        }
+
+        true
+    }
+
+    pub fn lines(&self, count: usize) -> Vec<String> {
+        self.lines.lock().unwrap().last(count)
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn index(&self) -> usize {
+        match self {
+            Stream::Stdout => 0,
+            Stream::Stderr => 1,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonLine<'a> {
+    ts: String,
+    server: &'a str,
+    stream: Stream,
+    line: &'a str,
+}
+
+enum Sink {
+    Inherit,
+    Prefix {
+        stdout: String,
+        stderr: String,
+        colored: bool,
+    },
+    File(LogFile),
+    Json(Option<LogFile>),
+    #[cfg(unix)]
+    Syslog(Syslog),
+}
+
+struct Source {
+    name: String,
+    timestamps: Option<TimestampFormat>,
+    started: Instant,
+}
+
+struct Capture {
+    source: Source,
+    sinks: Vec<Sink>,
+    filter: Option<LineFilter>,
+    tail: OutputTail,
+}
+
+impl Capture {
+    fn write_line(&mut self, stream: Stream, line: &[u8], captured: Instant) -> io::Result<()> {
+        if let Some(filter) = &self.filter {
+            if !filter.matches(line) {
+                return Ok(());
+            }
+        }
+
+        self.tail.push(line);
+
+        for sink in self.sinks.iter_mut() {
+            sink.write_line(&self.source, stream, line, captured)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Sink {
+    fn is_console(&self) -> bool {
+        matches!(self, Sink::Inherit | Sink::Prefix { .. } | Sink::Json(None))
+    }
+
+    fn write_line(
+        &mut self,
+        source: &Source,
+        stream: Stream,
+        line: &[u8],
+        captured: Instant,
+    ) -> io::Result<()> {
+        match self {
+            Sink::Inherit => match stream {
+                Stream::Stdout => console::write_line(captured, line.to_vec()),
+                Stream::Stderr => io::stderr().lock().write_all(line),
+            },
+            Sink::Prefix {
+                stdout,
+                stderr,
+                colored,
+            } => {
+                let line = with_timestamp(source.timestamps, source.started, line);
+                let line = match stream {
+                    Stream::Stdout => [stdout.as_bytes(), b" ", &line].concat(),
+                    Stream::Stderr if *colored => {
+                        let content = line.trim_ascii_end();
+
+                        [
+                            stderr.as_bytes(),
+                            format!(" \x1b[{}m", STDERR_COLOR).as_bytes(),
+                            content,
+                            b"\x1b[0m",
+                            &line[content.len()..],
+                        ]
+                        .concat()
+                    }
+                    Stream::Stderr => [stderr.as_bytes(), b" ", &line].concat(),
+                };
+
+                console::write_line(captured, line)
+            }
+            Sink::File(log_file) => {
+                let line = with_timestamp(source.timestamps, source.started, line);
+
+                log_file.write(&line)
+            }
+            Sink::Json(log_file) => {
+                let line = String::from_utf8_lossy(line);
+                let mut json = serde_json::to_vec(&JsonLine {
+                    ts: format_timestamp(TimestampFormat::Rfc3339, source.started),
+                    server: &source.name,
+                    stream,
+                    line: line.trim_end_matches(['\r', '\n']),
+                })?;
+
+                json.push(b'\n');
+
+                match log_file {
+                    Some(log_file) => log_file.write(&json),
+                    None => console::write_line(captured, json),
+                }
+            }
+            #[cfg(unix)]
+            Sink::Syslog(syslog) => match stream {
+                Stream::Stdout => syslog.send(syslog::SEVERITY_INFO, line),
+                Stream::Stderr => syslog.send(syslog::SEVERITY_ERROR, line),
+            },
+        }
+    }
+}
+
+struct LogFile {
+    template: String,
+    name: String,
+    max_size: Option<u64>,
+    max_files: u32,
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(name: &str, output: &Output) -> anyhow::Result<LogFile> {
+        LogFile::new(
+            output.path.clone().unwrap_or(DEFAULT_LOG_PATH.to_string()),
+            name,
+            output.max_size,
+            output.max_files.unwrap_or(DEFAULT_MAX_FILES),
+        )
+    }
+
+    fn new(
+        template: String,
+        name: &str,
+        max_size: Option<u64>,
+        max_files: u32,
+    ) -> anyhow::Result<LogFile> {
+        let path = resolve_log_path(&template, name);
+        let (file, size) =
+            open_log_file(&path).context(format!("Could not open log file {}", path.display()))?;
+
+        Ok(LogFile {
+            template,
+            name: name.to_string(),
+            max_size,
+            max_files,
+            path,
+            file,
+            size,
+        })
+    }
+
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        let path = resolve_log_path(&self.template, &self.name);
+
+        if path != self.path {
+            (self.file, self.size) = open_log_file(&path)?;
+            self.path = path;
+        }
+
+        if let Some(max_size) = self.max_size {
+            if self.size > 0 && self.size + line.len() as u64 > max_size {
+                self.rotate()?;
+            }
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |index: u32| PathBuf::from(format!("{}.{}", self.path.display(), index));
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated(self.max_files);
+
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+
+            for index in (1..self.max_files).rev() {
+                let from = rotated(index);
+
+                if from.exists() {
+                    fs::rename(from, rotated(index + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, rotated(1))?;
+        }
+
+        (self.file, self.size) = open_log_file(&self.path)?;
+
+        Ok(())
+    }
+}
+
+fn format_timestamp(format: TimestampFormat, started: Instant) -> String {
+    match format {
+        TimestampFormat::Rfc3339 => time::OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+        TimestampFormat::Relative => format!("+{:.3}s", started.elapsed().as_secs_f64()),
+    }
+}
+
+fn with_timestamp(timestamps: Option<TimestampFormat>, started: Instant, line: &[u8]) -> Vec<u8> {
+    match timestamps {
+        Some(format) => [format_timestamp(format, started).as_bytes(), b" ", line].concat(),
+        None => line.to_vec(),
+    }
+}
+
+fn render_prefix(template: &str, name: &str, stream: Stream, color: Option<u8>) -> String {
+    let prefix = template
+        .replace("{name}", name)
+        .replace("{stream}", stream.as_str());
+
+    match color {
+        Some(color) => format!("\x1b[{}m{}\x1b[0m", color, prefix),
+        None => prefix,
+    }
+}
+
+fn resolve_log_path(template: &str, name: &str) -> PathBuf {
+    let date = time::OffsetDateTime::now_utc().date().to_string();
+
+    PathBuf::from(template.replace("{name}", name).replace("{date}", &date))
+}
+
+fn open_log_file(path: &PathBuf) -> io::Result<(File, u64)> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+
+    Ok((file, size))
+}
+
+// Output read from a stream together with the time it was read.
+type Chunk = (Instant, Vec<u8>);
+
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+
+        self.pending.extend_from_slice(chunk);
+
+        while let Some(index) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=index).collect();
+
+            lines.push(finish_line(&line[..line.len() - 1]));
+        }
+
+        if let Some(index) = self.pending.iter().rposition(|b| *b == b'\r') {
+            if index + 1 < self.pending.len() {
+                self.pending.drain(..=index);
+            }
+        }
+
+        lines
+    }
+
+    fn take_partial(&mut self) -> Option<Vec<u8>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let line: Vec<u8> = self.pending.drain(..).collect();
+
+        Some(finish_line(&line))
+    }
+}
+
+fn finish_line(line: &[u8]) -> Vec<u8> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = match line.iter().rposition(|b| *b == b'\r') {
+        Some(index) => &line[index + 1..],
+        None => line,
+    };
+
+    let mut line = String::from_utf8_lossy(line).into_owned();
+
+    line.push('\n');
+    line.into_bytes()
+}
+
+fn read_stream(
+    mut stream: impl Read + Send + 'static,
+    kind: Stream,
+    sender: Sender<(Stream, Option<Chunk>)>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0; 8192];
+
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => {
+                    if sender
+                        .send((kind, Some((Instant::now(), buffer[..length].to_vec()))))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+
+        sender.send((kind, None)).ok();
+    })
+}
+
+fn write_lines(receiver: Receiver<(Stream, Option<Chunk>)>, mut capture: Capture) {
+    let mut buffers = [LineBuffer::default(), LineBuffer::default()];
+    // When each stream was last read, the console sorts lines by it.
+    let mut captured = [Instant::now(); 2];
+    let mut failed = false;
+    let mut write = |capture: &mut Capture, kind: Stream, line: Vec<u8>, captured: Instant| {
+        if let Err(e) = capture.write_line(kind, &line, captured) {
+            if !failed {
+                warn!(
+                    "Could not write output of server {}: {}",
+                    capture.source.name, e
+                );
+                failed = true;
+            }
+        }
+    };
+
+    while capture.tail.open_streams.load(Ordering::SeqCst) > 0 {
+        let message = if buffers.iter().all(|b| b.pending.is_empty()) {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(PARTIAL_LINE_TIMEOUT)
+        };
+
+        match message {
+            Ok((kind, Some((read, chunk)))) => {
+                captured[kind.index()] = read;
+
+                for line in buffers[kind.index()].push(&chunk) {
+                    write(&mut capture, kind, line, read);
+                }
+            }
+            Ok((kind, None)) => {
+                if let Some(line) = buffers[kind.index()].take_partial() {
+                    write(&mut capture, kind, line, captured[kind.index()]);
+                }
+
+                capture.tail.open_streams.fetch_sub(1, Ordering::SeqCst);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                for kind in [Stream::Stdout, Stream::Stderr] {
+                    if let Some(line) = buffers[kind.index()].take_partial() {
+                        write(&mut capture, kind, line, captured[kind.index()]);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let on_close = capture.tail.on_close.lock().unwrap().take();
+
+    if let Some(handler) = on_close {
+        handler();
+    }
+}
+
+fn build_command(command: &str) -> Command {
+    let command_parts: Vec<&str> = command.split(' ').collect();
+    let mut cmd = Command::new(command_parts[0]);
+
+    cmd.args(&command_parts[1..]);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(0x08000000);
+    }
+
+    cmd
+}
+
+fn prefix_sink(name: &str, output: &Output) -> Sink {
+    let template = output.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+    let color = if env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal() {
+        let index = NEXT_PREFIX_COLOR.fetch_add(1, Ordering::Relaxed);
+
+        Some(PREFIX_COLORS[index % PREFIX_COLORS.len()])
+    } else {
+        None
+    };
+
+    let mut stderr = render_prefix(template, name, Stream::Stderr, color);
+
+    if let Some(marker) = &output.stderr_marker {
+        stderr = format!("{} {}", stderr, marker);
+    }
+
+    Sink::Prefix {
+        stdout: render_prefix(template, name, Stream::Stdout, color),
+        stderr,
+        colored: color.is_some(),
+    }
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn open_sinks(server: &Server, pid: u32) -> anyhow::Result<Vec<Sink>> {
+    let sink = match server.output.mode {
+        OutputMode::Inherit => Sink::Inherit,
+        OutputMode::Prefix => prefix_sink(&server.name, &server.output),
+        OutputMode::Tee => {
+            return Ok(vec![
+                prefix_sink(&server.name, &server.output),
+                Sink::File(LogFile::open(&server.name, &server.output)?),
+            ])
+        }
+        OutputMode::File => Sink::File(LogFile::open(&server.name, &server.output)?),
+        OutputMode::Json if server.output.path.is_none() => Sink::Json(None),
+        OutputMode::Json => Sink::Json(Some(LogFile::open(&server.name, &server.output)?)),
+        #[cfg(unix)]
+        OutputMode::Syslog => {
+            Sink::Syslog(Syslog::connect(&server.name, pid).context("Could not connect to syslog")?)
+        }
+        #[cfg(not(unix))]
+        OutputMode::Syslog => anyhow::bail!("Syslog output is only supported on Unix"),
+    };
+
+    Ok(vec![sink])
+}
+
+fn open_capture(
+    name: &str,
+    sinks: anyhow::Result<Vec<Sink>>,
+    timestamps: Option<TimestampFormat>,
+    settings: &OutputSettings,
+) -> anyhow::Result<Capture> {
+    let mut sinks = sinks?;
+
+    if let Some(log_dir) = &settings.log_dir {
+        let path = log_dir.join(format!("{}.log", name));
+
+        sinks.push(Sink::File(LogFile::new(
+            path.to_string_lossy().to_string(),
+            name,
+            None,
+            0,
+        )?));
+    }
+
+    Ok(Capture {
+        source: Source {
+            name: name.to_string(),
+            timestamps,
+            started: settings.started,
+        },
+        sinks,
+        filter: None,
+        tail: OutputTail::new(settings.tail_lines.max(settings.buffer_lines)),
+    })
+}
+
+fn spawn_captured(
+    mut cmd: Command,
+    command: &str,
+    open: impl FnOnce(u32) -> anyhow::Result<Capture>,
+) -> anyhow::Result<(Child, OutputTail)> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| spawn_error(&cmd, command))?;
+    let capture = match open(child.id()) {
+        Ok(capture) => capture,
+        Err(e) => {
+            child.kill().ok();
+
+            return Err(e);
+        }
+    };
+
+    let tail = capture.tail.clone();
+    let (sender, receiver) = mpsc::channel();
+    let mut threads = tail.threads.lock().unwrap();
+
+    if let Some(stdout) = child.stdout.take() {
+        tail.open_streams.fetch_add(1, Ordering::SeqCst);
+        threads.push(read_stream(stdout, Stream::Stdout, sender.clone()));
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        tail.open_streams.fetch_add(1, Ordering::SeqCst);
+        threads.push(read_stream(stderr, Stream::Stderr, sender));
+    }
+
+    threads.push(thread::spawn(move || write_lines(receiver, capture)));
+    drop(threads);
+
+    Ok((child, tail))
+}
+
+pub fn log_file_templates(server: &Server, log_dir: Option<&PathBuf>) -> Vec<String> {
+    let mut templates = Vec::new();
+
+    match server.output.mode {
+        OutputMode::File | OutputMode::Tee => templates.push(
+            server
+                .output
+                .path
+                .clone()
+                .unwrap_or(DEFAULT_LOG_PATH.to_string()),
+        ),
+        OutputMode::Json => templates.extend(server.output.path.clone()),
+        _ => {}
+    }
+
+    if let Some(log_dir) = log_dir {
+        templates.push(
+            log_dir
+                .join(format!("{}.log", server.name))
+                .to_string_lossy()
+                .to_string(),
+        );
+    }
+
+    templates
+}
+
+#[cfg(unix)]
+pub fn kill_process_group(pid: u32) {
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+}
+
+fn has_terminal() -> bool {
+    io::stdin().is_terminal() || io::stdout().is_terminal() || io::stderr().is_terminal()
+}
+
+// A background process group is stopped with SIGTTIN/SIGTTOU as soon as it touches the
+// terminal, so commands only get their own group when there is no terminal.
+#[cfg(unix)]
+pub fn detaches_commands() -> bool {
+    !has_terminal()
+}
+
+#[cfg(unix)]
+pub fn signal_command(pid: u32, signal: libc::c_int) {
+    let target = if detaches_commands() {
+        -(pid as i32)
+    } else {
+        pid as i32
+    };
+
+    unsafe {
+        libc::kill(target, signal);
+    }
+}
+
+#[cfg(windows)]
+pub fn kill_process_tree(pid: u32) {
+    Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok();
+}
+
+#[cfg(unix)]
+pub struct ChildExitWatcher(signal_hook::iterator::Handle);
+
+#[cfg(unix)]
+impl ChildExitWatcher {
+    pub fn start(sender: Sender<()>) -> anyhow::Result<ChildExitWatcher> {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGCHLD])
+            .context("Could not register SIGCHLD handler")?;
+        let handle = signals.handle();
+
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                if sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(ChildExitWatcher(handle))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ChildExitWatcher {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+fn spawn_inherited(mut cmd: Command, command: &str) -> anyhow::Result<Child> {
+    let child = cmd.spawn().with_context(|| spawn_error(&cmd, command))?;
+
+    Ok(child)
+}
+
+fn spawn_error(cmd: &Command, command: &str) -> String {
+    format!(
+        "Could not start program {} of command '{}'",
+        cmd.get_program().to_string_lossy(),
+        command
+    )
+}
+
+pub fn run_command(
+    command: &str,
+    prefix: Option<&str>,
+    env: &[(String, String)],
+    settings: &OutputSettings,
+) -> anyhow::Result<(Child, Option<OutputTail>)> {
+    let mut cmd = build_command(command);
+
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
+
+    #[cfg(unix)]
+    if detaches_commands() {
+        use std::os::unix::process::CommandExt;
+
+        cmd.process_group(0);
+    }
+
+    if settings.log_dir.is_none() && !settings.quiet_command && prefix.is_none() {
+        return Ok((spawn_inherited(cmd, command)?, None));
+    }
+
+    let sinks = match prefix {
+        _ if settings.quiet_command => vec![],
+        Some(name) => vec![prefix_sink(name, &Output::default())],
+        None => vec![Sink::Inherit],
+    };
+    let log_name = match prefix {
+        Some(name) => format!("{}-{}", COMMAND_LOG_NAME, name),
+        None => COMMAND_LOG_NAME.to_string(),
+    };
+    let (child, tail) = spawn_captured(cmd, command, |_| {
+        open_capture(&log_name, Ok(sinks), None, settings)
+    })?;
+
+    Ok((child, Some(tail)))
+}
+
+pub fn run_server(
+    config: &Config,
+    server: &Server,
+    settings: &OutputSettings,
+) -> anyhow::Result<(Child, Option<OutputTail>)> {
+    let (cmd, command) = match server.server_type {
+        ServerType::Command | ServerType::Task => match &server.host {
+            Some(host) => (
+                ssh::run_command(server, host),
+                format!("ssh {} {}", host, server.command),
+            ),
+            None => (build_command(&server.command), server.command.to_string()),
+        },
+        ServerType::Docker => (
+            docker::run_command(server),
+            format!("docker run {}", server.image.as_deref().unwrap_or_default()),
+        ),
+        ServerType::Compose => (
+            config.compose.logs_command(server.service()),
+            format!("docker compose logs {}", server.service()),
+        ),
+        ServerType::NpmScript => npm::run_command(server),
+        ServerType::Cargo => cargo::run_command(server)?,
+        ServerType::Static | ServerType::Mock => {
+            anyhow::bail!("Server {} is served in-process", server.name)
+        }
+    };
+
+    if server.output.mode == OutputMode::Inherit
+        && server.output.filter.is_none()
+        && settings.log_dir.is_none()
+        && !settings.quiet_servers
+    {
+        return Ok((spawn_inherited(cmd, &command)?, None));
+    }
+
+    let filter = match &server.output.filter {
+        Some(filter) => Some(
+            LineFilter::new(filter)
+                .context(format!("Invalid output filter for server {}", server.name))?,
+        ),
+        None => None,
+    };
+    let (child, tail) = spawn_captured(cmd, &command, |pid| {
+        let sinks = open_sinks(server, pid).map(|mut sinks| {
+            if settings.quiet_servers {
+                sinks.retain(|sink| !sink.is_console());
+            }
+
+            sinks
+        });
+        let mut capture = open_capture(
+            &server.name,
+            sinks,
+            server.output.timestamps.or(settings.timestamps),
+            settings,
+        )?;
+
+        capture.filter = filter;
+
+        Ok(capture)
+    })?;
+
+    Ok((child, Some(tail)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_crlf_split_across_chunks() {
+        let mut buffer = LineBuffer::default();
+
+        assert!(buffer.push(b"hello\r").is_empty());
+        assert_eq!(buffer.push(b"\nworld\r\n"), vec![b"hello\n", b"world\n"]);
+        assert_eq!(buffer.take_partial(), None);
+    }
+
+    #[test]
+    fn keeps_text_after_last_carriage_return() {
+        let mut buffer = LineBuffer::default();
+
+        assert!(buffer.push(b"progress 1\rprogress 2\r").is_empty());
+        assert!(buffer.push(b"progress 3\rdo").is_empty());
+        assert_eq!(buffer.push(b"ne\n"), vec![b"done\n"]);
+    }
+
+    #[test]
+    fn returns_trailing_partial_line() {
+        let mut buffer = LineBuffer::default();
+
+        assert_eq!(buffer.push(b"first\nsecond"), vec![b"first\n"]);
+        assert_eq!(buffer.take_partial(), Some(b"second\n".to_vec()));
+        assert_eq!(buffer.take_partial(), None);
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        let mut buffer = LineBuffer::default();
+
+        assert!(buffer.push(b"caf\xc3").is_empty());
+        assert_eq!(buffer.push(b"\xa9\n"), vec!["caf\u{e9}\n".as_bytes()]);
+        assert_eq!(finish_line(b"caf\xe9"), "caf\u{fffd}\n".as_bytes());
+        assert_eq!(finish_line(b"old\rnew\r"), b"new\n");
+    }
+
+    fn write_log_lines(log_file: &mut LogFile, lines: &[&str]) {
+        for line in lines {
+            log_file.write(line.as_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn rotates_log_file_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = format!("{}/{{name}}.log", dir.path().display());
+        let mut log_file = LogFile::new(template, "web", Some(10), 2).unwrap();
+
+        write_log_lines(
+            &mut log_file,
+            &["line 1\n", "line 2\n", "line 3\n", "line 4\n"],
+        );
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+
+        assert_eq!(read("web.log"), "line 4\n");
+        assert_eq!(read("web.log.1"), "line 3\n");
+        assert_eq!(read("web.log.2"), "line 2\n");
+        assert!(!dir.path().join("web.log.3").exists());
+    }
+
+    #[test]
+    fn truncates_log_file_without_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = format!("{}/{{name}}.log", dir.path().display());
+        let mut log_file = LogFile::new(template, "web", Some(10), 0).unwrap();
+
+        write_log_lines(&mut log_file, &["line 1\n", "line 2\n"]);
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("web.log")).unwrap(),
+            "line 2\n"
+        );
+        assert!(!dir.path().join("web.log.1").exists());
+    }
+
+    #[test]
+    fn filters_lines() {
+        let filter = LineFilter::new(&Filter {
+            include: vec!["^GET".to_string()],
+            exclude: vec!["/health$".to_string()],
+        })
+        .unwrap();
+
+        assert!(filter.matches(b"GET /\r\n"));
+        assert!(!filter.matches(b"GET /health\n"));
+        assert!(!filter.matches(b"POST /\n"));
+        assert!(LineFilter::new(&Filter::default())
+            .unwrap()
+            .matches(b"anything\n"));
+    }
+
+    #[test]
+    fn renders_prefix() {
+        assert_eq!(
+            render_prefix("[{name}:{stream}]", "web", Stream::Stderr, None),
+            "[web:stderr]"
+        );
+        assert_eq!(
+            render_prefix("[{name}]", "web", Stream::Stdout, Some(36)),
+            "\x1b[36m[web]\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn adds_stderr_marker_to_stderr_prefix() {
+        let output = Output {
+            prefix: Some("[{name}]".to_string()),
+            stderr_marker: Some("!!".to_string()),
+            ..Default::default()
+        };
+        let Sink::Prefix { stdout, stderr, .. } = prefix_sink("web", &output) else {
+            panic!("Expected a prefix sink");
+        };
+
+        assert!(!stdout.ends_with("!!"));
+        assert!(stderr.ends_with("[web] !!") || stderr.ends_with("[web]\x1b[0m !!"));
+    }
+
+    #[test]
+    fn adds_timestamp_to_line() {
+        let started = Instant::now();
+
+        assert_eq!(with_timestamp(None, started, b"line\n"), b"line\n");
+
+        let line = with_timestamp(Some(TimestampFormat::Relative), started, b"line\n");
+        let line = String::from_utf8(line).unwrap();
+        let (timestamp, rest) = line.split_once(' ').unwrap();
+
+        assert!(timestamp.starts_with('+') && timestamp.ends_with('s'));
+        assert_eq!(rest, "line\n");
+
+        let line = with_timestamp(Some(TimestampFormat::Rfc3339), started, b"line\n");
+        let line = String::from_utf8(line).unwrap();
+        let (timestamp, rest) = line.split_once(' ').unwrap();
+
+        assert_eq!(timestamp.as_bytes()[10], b'T');
+        assert!(timestamp.ends_with('Z'));
+        assert_eq!(rest, "line\n");
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Context;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::events::Event;
+
+const BAR_WIDTH: f64 = 40.0;
+
+struct Timing {
+    name: String,
+    start: Duration,
+    duration: Duration,
+}
+
+#[derive(Default)]
+struct Timings {
+    finished: Vec<Timing>,
+    open: HashMap<String, Instant>,
+}
+
+pub struct StartupProfile {
+    started: Instant,
+    timings: Mutex<Timings>,
+}
+
+impl StartupProfile {
+    pub fn new(started: Instant) -> StartupProfile {
+        StartupProfile {
+            started,
+            timings: Mutex::new(Timings::default()),
+        }
+    }
+
+    pub fn record(&self, name: &str, start: Instant, end: Instant) {
+        self.timings.lock().unwrap().finished.push(Timing {
+            name: name.to_string(),
+            start: start.duration_since(self.started),
+            duration: end.duration_since(start),
+        });
+    }
+
+    pub fn handle(&self, event: &Event) {
+        match event {
+            Event::ServerStarting { name } => self.open(format!("spawn {}", name)),
+            Event::ServerSpawned { name, .. } => {
+                self.close(&format!("spawn {}", name));
+                self.open(format!("first successful check {}", name));
+            }
+            Event::ServerReady { name } => self.close(&format!("first successful check {}", name)),
+            Event::ServersReady => {
+                let mut timings = self.timings.lock().unwrap();
+
+                timings.open.clear();
+                timings.finished.push(Timing {
+                    name: "startup".to_string(),
+                    start: Duration::ZERO,
+                    duration: self.started.elapsed(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn open(&self, name: String) {
+        self.timings
+            .lock()
+            .unwrap()
+            .open
+            .insert(name, Instant::now());
+    }
+
+    fn close(&self, name: &str) {
+        let mut timings = self.timings.lock().unwrap();
+
+        if let Some(start) = timings.open.remove(name) {
+            timings.finished.push(Timing {
+                name: name.to_string(),
+                start: start.duration_since(self.started),
+                duration: start.elapsed(),
+            });
+        }
+    }
+
+    pub fn print(&self) {
+        let timings = self.timings.lock().unwrap();
+        let total = timings
+            .finished
+            .iter()
+            .map(|t| t.start + t.duration)
+            .max()
+            .unwrap_or_default()
+            .as_secs_f64()
+            .max(f64::EPSILON);
+        let width = timings
+            .finished
+            .iter()
+            .map(|t| t.name.len())
+            .max()
+            .unwrap_or_default();
+
+        eprintln!("----- Startup profile -----");
+
+        for timing in &timings.finished {
+            let offset = (timing.start.as_secs_f64() / total * BAR_WIDTH) as usize;
+            let length = ((timing.duration.as_secs_f64() / total * BAR_WIDTH) as usize).max(1);
+
+            eprintln!(
+                "{:width$} {:>8.1}ms |{}{}",
+                timing.name,
+                timing.duration.as_secs_f64() * 1000.0,
+                " ".repeat(offset),
+                "#".repeat(length),
+                width = width
+            );
+        }
+    }
+
+    pub fn write_chrome_trace(&self, path: &Path) -> anyhow::Result<()> {
+        let timings = self.timings.lock().unwrap();
+        let events: Vec<_> = timings
+            .finished
+            .iter()
+            .enumerate()
+            .map(|(index, timing)| {
+                json!({
+                    "name": timing.name,
+                    "ph": "X",
+                    "ts": timing.start.as_micros() as u64,
+                    "dur": timing.duration.as_micros() as u64,
+                    "pid": std::process::id(),
+                    "tid": index + 1,
+                })
+            })
+            .collect();
+
+        fs::write(path, json!({ "traceEvents": events }).to_string()).context(format!(
+            "Could not write startup profile {}",
+            path.display()
+        ))
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Request, Response};
+
+use crate::config::Config;
+
+const SKIPPED_HEADERS: [&str; 4] = ["host", "connection", "content-length", "transfer-encoding"];
+
+#[derive(serde::Deserialize)]
+pub struct Route {
+    pub path: Option<String>,
+    pub host: Option<String>,
+}
+
+struct Target {
+    path: Option<String>,
+    host: Option<String>,
+    origin: String,
+}
+
+pub fn serve(port: u16, config: &Config) -> anyhow::Result<()> {
+    let targets: Arc<Vec<Target>> = Arc::new(
+        config
+            .servers
+            .iter()
+            .filter_map(|server| {
+                let route = server.route.as_ref()?;
+                let url = reqwest::Url::parse(server.url.as_ref()?).ok()?;
+
+                Some(Target {
+                    path: route
+                        .path
+                        .as_ref()
+                        .map(|p| p.trim_end_matches('/').to_string()),
+                    host: route.host.as_ref().map(|h| h.to_lowercase()),
+                    origin: url.origin().ascii_serialization(),
+                })
+            })
+            .collect(),
+    );
+    let client = Client::builder().redirect(Policy::none()).build()?;
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("Could not start proxy on port {}: {}", port, e))?;
+
+    info!("Proxy listening on http://127.0.0.1:{}", port);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let targets = Arc::clone(&targets);
+            let client = client.clone();
+
+            thread::spawn(move || forward(request, &targets, &client));
+        }
+    });
+
+    Ok(())
+}
+
+fn find_target<'a>(targets: &'a [Target], host: Option<&str>, path: &str) -> Option<&'a Target> {
+    let host = host.map(|h| h.split(':').next().unwrap_or(h).to_lowercase());
+
+    targets
+        .iter()
+        .filter(|target| match &target.host {
+            Some(target_host) => host.as_ref() == Some(target_host),
+            None => true,
+        })
+        .filter(|target| match &target.path {
+            Some(prefix) => {
+                path == prefix
+                    || path.starts_with(&format!("{}/", prefix))
+                    || path.starts_with(&format!("{}?", prefix))
+            }
+            None => true,
+        })
+        .max_by_key(|target| {
+            (
+                target.host.is_some(),
+                target.path.as_ref().map_or(0, String::len),
+            )
+        })
+}
+
+fn forward(mut request: Request, targets: &[Target], client: &Client) {
+    let host = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Host"))
+        .map(|h| h.value.to_string());
+    let Some(target) = find_target(targets, host.as_deref(), request.url()) else {
+        request
+            .respond(Response::from_string("No route").with_status_code(404))
+            .ok();
+        return;
+    };
+    let url = format!("{}{}", target.origin, request.url());
+    let mut body = Vec::new();
+
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        warn!("Could not read proxy request for {}: {}", url, e);
+        return;
+    }
+
+    let method = match reqwest::Method::from_bytes(request.method().as_str().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => {
+            request
+                .respond(Response::from_string("Bad method").with_status_code(400))
+                .ok();
+            return;
+        }
+    };
+    let mut upstream = client.request(method, &url).body(body);
+
+    for header in request.headers() {
+        if !is_skipped(header.field.as_str().as_str()) {
+            upstream = upstream.header(header.field.as_str().as_str(), header.value.as_str());
+        }
+    }
+
+    let response = match upstream.send() {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Could not proxy request to {}: {}", url, e);
+            request
+                .respond(Response::from_string("Bad gateway").with_status_code(502))
+                .ok();
+            return;
+        }
+    };
+    let status = response.status().as_u16();
+    let headers: Vec<Header> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !is_skipped(name.as_str()))
+        .filter_map(|(name, value)| Header::from_bytes(name.as_str(), value.as_bytes()).ok())
+        .collect();
+    let body = match response.bytes() {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Could not read proxied response from {}: {}", url, e);
+            request
+                .respond(Response::from_string("Bad gateway").with_status_code(502))
+                .ok();
+            return;
+        }
+    };
+    let mut response = Response::from_data(body.to_vec()).with_status_code(status);
+
+    for header in headers {
+        response.add_header(header);
+    }
+
+    request.respond(response).ok();
+}
+
+fn is_skipped(name: &str) -> bool {
+    SKIPPED_HEADERS
+        .iter()
+        .any(|skipped| skipped.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,62 @@
+use anyhow::Context;
+use log::info;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+pub struct ReadinessGate {
+    closed: Arc<AtomicBool>,
+    file: Option<PathBuf>,
+}
+
+impl ReadinessGate {
+    pub fn open(port: Option<u16>, file: Option<&PathBuf>) -> anyhow::Result<ReadinessGate> {
+        let closed = Arc::new(AtomicBool::new(false));
+
+        if let Some(port) = port {
+            let listener = TcpListener::bind(("127.0.0.1", port))
+                .context(format!("Could not open ready port {}", port))?;
+
+            listener.set_nonblocking(true)?;
+
+            let closed = Arc::clone(&closed);
+
+            thread::spawn(move || {
+                while !closed.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(100))
+                        }
+                        Err(_) => break,
+                    }
+                }
+            });
+
+            info!("Opened ready port {}", port);
+        }
+
+        if let Some(file) = file {
+            fs::write(file, "")
+                .context(format!("Could not create ready file {}", file.display()))?;
+        }
+
+        Ok(ReadinessGate {
+            closed,
+            file: file.cloned(),
+        })
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+
+        if let Some(file) = &self.file {
+            fs::remove_file(file).ok();
+        }
+    }
+}
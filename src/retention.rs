@@ -0,0 +1,240 @@
+use log::{info, warn};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DATE_PATTERN: &str = "[0-9]{4}-[0-9]{2}-[0-9]{2}";
+
+#[derive(serde::Deserialize)]
+pub struct Retention {
+    pub max_age_days: Option<u64>,
+    pub max_total_size: Option<u64>,
+}
+
+struct LogFileInfo {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+pub fn prune_logs(retention: &Retention, templates: &[(String, String)]) -> anyhow::Result<()> {
+    let mut files = find_log_files(templates)?;
+
+    files.sort_by_key(|f| f.modified);
+
+    if let Some(max_age_days) = retention.max_age_days {
+        let max_age = Duration::from_secs(max_age_days * SECONDS_PER_DAY);
+        let now = SystemTime::now();
+
+        files.retain(|f| {
+            let expired = now
+                .duration_since(f.modified)
+                .is_ok_and(|age| age > max_age);
+
+            !(expired && remove_log_file(&f.path))
+        });
+    }
+
+    if let Some(max_total_size) = retention.max_total_size {
+        let mut total_size: u64 = files.iter().map(|f| f.size).sum();
+
+        for f in &files {
+            if total_size <= max_total_size {
+                break;
+            }
+
+            if remove_log_file(&f.path) {
+                total_size -= f.size;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn remove_log_file(path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(_) => {
+            info!("Removed old log file {}", path.display());
+            true
+        }
+        Err(e) => {
+            warn!("Could not remove old log file {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+fn find_log_files(templates: &[(String, String)]) -> anyhow::Result<Vec<LogFileInfo>> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+
+    for (template, name) in templates {
+        let template = PathBuf::from(template.replace("{name}", name));
+        let directories = match template.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => find_directories(parent)?,
+            _ => vec![PathBuf::from(".")],
+        };
+        let file_name = match template.file_name() {
+            Some(file_name) => file_name.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let pattern = date_pattern(&file_name, "(\\.[0-9]+)?")?;
+
+        for directory in directories {
+            let entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                if !pattern.is_match(&entry.file_name().to_string_lossy())
+                    || !seen.insert(path.clone())
+                {
+                    continue;
+                }
+
+                if let Ok(metadata) = entry.metadata() {
+                    if metadata.is_file() {
+                        files.push(LogFileInfo {
+                            path,
+                            size: metadata.len(),
+                            modified: metadata.modified()?,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+// Log directories may contain {date} as well, e.g. `logs/{date}/{name}.log`.
+fn find_directories(template: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut directories = vec![PathBuf::new()];
+
+    for component in template.components() {
+        let component = component.as_os_str().to_string_lossy();
+
+        if !component.contains("{date}") {
+            for directory in directories.iter_mut() {
+                directory.push(component.as_ref());
+            }
+
+            continue;
+        }
+
+        let pattern = date_pattern(&component, "")?;
+
+        directories = directories
+            .iter()
+            .flat_map(|directory| {
+                let parent = if directory.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    directory.as_path()
+                };
+
+                fs::read_dir(parent)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter(|entry| {
+                        entry.path().is_dir()
+                            && pattern.is_match(&entry.file_name().to_string_lossy())
+                    })
+                    .map(|entry| directory.join(entry.file_name()))
+            })
+            .collect();
+    }
+
+    Ok(directories)
+}
+
+fn date_pattern(template: &str, suffix: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!(
+        "^{}{}$",
+        regex::escape(template).replace("\\{date\\}", DATE_PATTERN),
+        suffix
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    const DAY: Duration = Duration::from_secs(SECONDS_PER_DAY);
+
+    fn write_log(path: &Path, size: usize, age: Duration) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; size]).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn removes_expired_logs_in_date_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("2026-01-01/web.log");
+        let rotated = dir.path().join("2026-01-01/web.log.1");
+        let recent = dir.path().join("2026-01-02/web.log");
+        let other = dir.path().join("2026-01-01/api.log");
+        let template = format!("{}/{{date}}/{{name}}.log", dir.path().display());
+
+        write_log(&old, 10, 3 * DAY);
+        write_log(&rotated, 10, 3 * DAY);
+        write_log(&recent, 10, Duration::ZERO);
+        write_log(&other, 10, 3 * DAY);
+
+        prune_logs(
+            &Retention {
+                max_age_days: Some(2),
+                max_total_size: None,
+            },
+            &[(template, "web".to_string())],
+        )
+        .unwrap();
+
+        assert!(!old.exists());
+        assert!(!rotated.exists());
+        assert!(recent.exists());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn removes_oldest_logs_above_total_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let oldest = dir.path().join("web-2026-01-01.log");
+        let older = dir.path().join("web-2026-01-02.log");
+        let newest = dir.path().join("web-2026-01-03.log");
+        let template = format!("{}/{{name}}-{{date}}.log", dir.path().display());
+
+        write_log(&oldest, 10, 3 * DAY);
+        write_log(&older, 10, 2 * DAY);
+        write_log(&newest, 10, DAY);
+
+        prune_logs(
+            &Retention {
+                max_age_days: None,
+                max_total_size: Some(20),
+            },
+            &[(template, "web".to_string())],
+        )
+        .unwrap();
+
+        assert!(!oldest.exists());
+        assert!(older.exists());
+        assert!(newest.exists());
+    }
+}
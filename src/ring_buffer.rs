@@ -0,0 +1,35 @@
+use std::collections::VecDeque;
+
+pub struct RingBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Clone> RingBuffer<T> {
+    pub fn new(capacity: usize) -> RingBuffer<T> {
+        RingBuffer {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+
+        self.items.push_back(item);
+    }
+
+    pub fn last(&self, count: usize) -> Vec<T> {
+        self.items
+            .iter()
+            .skip(self.items.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,1620 @@
+use anyhow::{anyhow, bail, Context};
+use log::{info, warn};
+use regex::Regex;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::chaos::ChaosMonkey;
+use crate::config::{CheckMethod, CheckType, Config, Server, ServerType};
+use crate::events::{Event, Events};
+use crate::in_process::InProcessServer;
+#[cfg(windows)]
+use crate::process::kill_process_tree;
+#[cfg(unix)]
+use crate::process::{detaches_commands, kill_process_group, signal_command, ChildExitWatcher};
+use crate::process::{log_file_templates, run_command, run_server, OutputSettings, OutputTail};
+use crate::readiness_gate::ReadinessGate;
+use crate::retention::prune_logs;
+use crate::ring_buffer::RingBuffer;
+use crate::{console, docker, mock, ssh, static_files};
+
+const HEALTH_HISTORY_LENGTH: usize = 10;
+const OUTPUT_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const STATE_FILE: &str = "state.json";
+#[cfg(unix)]
+const COMMAND_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(unix)]
+const COMMAND_STOP_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(PartialEq, Eq)]
+pub enum ServerStatus {
+    Waiting,
+    Running,
+}
+
+struct HealthClient {
+    client: Client,
+    head: AtomicBool,
+    timeout: Option<Duration>,
+    container: String,
+}
+
+impl HealthClient {
+    fn new(config: &Config, server: &Server, external: bool) -> anyhow::Result<HealthClient> {
+        let mut builder = Client::builder().tcp_keepalive(Duration::from_secs(30));
+
+        if let Some(timeout) = config.check_timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        // Containers started by something else can't have the generated name.
+        let container = match &server.container {
+            None if external => server.name.to_string(),
+            _ => docker::container_name(server),
+        };
+
+        Ok(HealthClient {
+            client: builder.build().context("Could not create HTTP client")?,
+            head: AtomicBool::new(server.check_method == CheckMethod::Head),
+            timeout: config.check_timeout,
+            container,
+        })
+    }
+}
+
+struct ServerProcess {
+    name: String,
+    // None for servers that are started by something else or served in-process.
+    process: Option<Child>,
+    in_process: Option<InProcessServer>,
+    output: Option<OutputTail>,
+    stopped: bool,
+}
+
+impl ServerProcess {
+    fn pid(&self) -> u32 {
+        match (&self.process, &self.in_process) {
+            (Some(process), _) => process.id(),
+            (None, Some(_)) => std::process::id(),
+            (None, None) => 0,
+        }
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        match &mut self.process {
+            Some(process) => process.try_wait(),
+            None => Ok(None),
+        }
+    }
+}
+
+pub struct ServerRunnerBuilder {
+    config: Config,
+    output_settings: Option<OutputSettings>,
+    events: Events,
+}
+
+impl ServerRunnerBuilder {
+    pub fn new(config: Config) -> ServerRunnerBuilder {
+        ServerRunnerBuilder {
+            config,
+            output_settings: None,
+            events: Events::default(),
+        }
+    }
+
+    pub fn output_settings(mut self, output_settings: OutputSettings) -> ServerRunnerBuilder {
+        self.output_settings = Some(output_settings);
+        self
+    }
+
+    pub fn on_event<F>(mut self, handler: F) -> ServerRunnerBuilder
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.events.add(Arc::new(handler));
+        self
+    }
+
+    pub fn start(self) -> anyhow::Result<ServerManager> {
+        let output_settings = self
+            .output_settings
+            .unwrap_or_else(|| OutputSettings::from_config(&self.config));
+
+        ServerManager::start_with_events(self.config, output_settings, self.events)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ServerState {
+    pub name: String,
+    pub url: Option<String>,
+    pub pid: u32,
+    pub running: bool,
+    pub ready: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct CheckRecord {
+    pub time: String,
+    pub attempt: u32,
+    pub result: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ServerSnapshot {
+    #[serde(flatten)]
+    pub state: ServerState,
+    pub attempts: u32,
+    pub history: Vec<CheckRecord>,
+}
+
+struct Health {
+    attempts: u32,
+    history: RingBuffer<CheckRecord>,
+}
+
+#[derive(Clone)]
+pub struct ServerManager {
+    config: Arc<Config>,
+    output_settings: Arc<OutputSettings>,
+    server_processes: Arc<Mutex<Vec<ServerProcess>>>,
+    ready_servers: Arc<Mutex<HashSet<String>>>,
+    health: Arc<Mutex<HashMap<String, Health>>>,
+    readiness_gate: Arc<Mutex<Option<ReadinessGate>>>,
+    running_commands: Arc<Mutex<Vec<(String, u32)>>>,
+    command_count: Arc<AtomicUsize>,
+    stopping: Arc<AtomicBool>,
+    state_dir: Arc<Mutex<Option<TempDir>>>,
+    external: bool,
+    events: Events,
+}
+
+impl ServerManager {
+    pub fn start(config: Config, output_settings: OutputSettings) -> anyhow::Result<ServerManager> {
+        ServerManager::start_with_events(config, output_settings, Events::default())
+    }
+
+    fn start_with_events(
+        config: Config,
+        output_settings: OutputSettings,
+        events: Events,
+    ) -> anyhow::Result<ServerManager> {
+        console::init(config.sort_window);
+
+        if let Some(retention) = &config.log_retention {
+            let mut templates = Vec::new();
+
+            for server in &config.servers {
+                for template in log_file_templates(server, output_settings.log_dir.as_ref()) {
+                    templates.push((template, server.name.to_string()));
+                }
+            }
+
+            if let Some(log_dir) = &output_settings.log_dir {
+                templates.push((
+                    log_dir.join("command.log").to_string_lossy().to_string(),
+                    "command".to_string(),
+                ));
+            }
+
+            prune_logs(retention, &templates).context("Could not prune old log files")?;
+        }
+
+        if config.uses_compose() {
+            config.compose.up()?;
+        }
+
+        let server_processes = match start_servers(&config, &output_settings, &events) {
+            Ok(server_processes) => server_processes,
+            Err(e) => {
+                if config.uses_compose() {
+                    config.compose.down().ok();
+                }
+
+                return Err(e);
+            }
+        };
+        let server_manager =
+            ServerManager::with_processes(config, output_settings, server_processes, events, false);
+
+        for (index, server_process) in server_manager
+            .server_processes
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            server_manager.watch_exit(index, server_process);
+        }
+
+        Ok(server_manager)
+    }
+
+    fn with_processes(
+        config: Config,
+        output_settings: OutputSettings,
+        server_processes: Vec<ServerProcess>,
+        events: Events,
+        external: bool,
+    ) -> ServerManager {
+        let finished_tasks = config
+            .servers
+            .iter()
+            .filter(|s| s.server_type == ServerType::Task)
+            .map(|s| s.name.to_string())
+            .collect();
+
+        ServerManager {
+            config: Arc::new(config),
+            output_settings: Arc::new(output_settings),
+            server_processes: Arc::new(Mutex::new(server_processes)),
+            ready_servers: Arc::new(Mutex::new(finished_tasks)),
+            health: Arc::new(Mutex::new(HashMap::new())),
+            readiness_gate: Arc::new(Mutex::new(None)),
+            running_commands: Arc::new(Mutex::new(Vec::new())),
+            command_count: Arc::new(AtomicUsize::new(0)),
+            stopping: Arc::new(AtomicBool::new(false)),
+            state_dir: Arc::new(Mutex::new(None)),
+            external,
+            events,
+        }
+    }
+
+    fn external(config: Config) -> ServerManager {
+        let server_processes = config
+            .servers
+            .iter()
+            .map(|server| ServerProcess {
+                name: server.name.to_string(),
+                process: None,
+                in_process: None,
+                output: None,
+                stopped: false,
+            })
+            .collect();
+        let output_settings = OutputSettings::from_config(&config);
+
+        ServerManager::with_processes(
+            config,
+            output_settings,
+            server_processes,
+            Events::default(),
+            true,
+        )
+    }
+
+    fn watch_exit(&self, index: usize, server_process: &ServerProcess) {
+        if self.config.servers[index].server_type == ServerType::Task {
+            return;
+        }
+
+        if let Some(output) = &server_process.output {
+            let server_manager = self.clone();
+            let pid = server_process.pid();
+
+            output.on_close(move || {
+                thread::spawn(move || server_manager.report_exit(index, pid));
+            });
+        }
+    }
+
+    fn report_exit(&self, index: usize, pid: u32) {
+        let started = Instant::now();
+
+        while started.elapsed() < OUTPUT_CLOSE_TIMEOUT {
+            let status = {
+                let mut server_processes = self.server_processes.lock().unwrap();
+                let server_process = &mut server_processes[index];
+
+                if server_process.stopped || server_process.pid() != pid {
+                    return;
+                }
+
+                server_process.try_wait()
+            };
+
+            if let Ok(Some(status)) = status {
+                let name = &self.config.servers[index].name;
+
+                if self.ready_servers.lock().unwrap().contains(name) {
+                    warn!("Server {} exited with {}", name, status);
+
+                    self.events.emit(Event::ServerCrashed {
+                        name: name.to_string(),
+                        status,
+                    });
+                }
+
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn wait_for_servers(&self, max_attempts: u32) -> anyhow::Result<()> {
+        self.wait_until_ready(max_attempts)?;
+        self.write_urls()?;
+
+        if self.config.ready_port.is_some() || self.config.ready_file.is_some() {
+            let gate =
+                ReadinessGate::open(self.config.ready_port, self.config.ready_file.as_ref())?;
+
+            *self.readiness_gate.lock().unwrap() = Some(gate);
+        }
+
+        self.events.emit(Event::ServersReady);
+
+        Ok(())
+    }
+
+    fn wait_until_ready(&self, max_attempts: u32) -> anyhow::Result<()> {
+        let mut attempts: HashMap<String, u32> = HashMap::new();
+        let clients = self
+            .config
+            .servers
+            .iter()
+            .map(|server| HealthClient::new(&self.config, server, self.external))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut next_checks = vec![Instant::now(); self.config.servers.len()];
+        let deadline = self
+            .config
+            .startup_timeout
+            .map(|timeout| Instant::now() + timeout);
+        #[cfg_attr(not(unix), allow(unused_variables))]
+        let (wake, woken) = mpsc::channel();
+        #[cfg(unix)]
+        let _child_exits = ChildExitWatcher::start(wake.clone())?;
+
+        loop {
+            let now = Instant::now();
+            let mut pending = Vec::new();
+            let stopped = self.stopped_servers();
+
+            for (index, server) in self.config.servers.iter().enumerate() {
+                if server.server_type == ServerType::Task || stopped[index] {
+                    continue;
+                }
+
+                let attempt = attempts.entry(server.name.to_string()).or_default();
+
+                if let Some(status) = self.exit_status(index)? {
+                    return self.crashed(index, server, *attempt, status);
+                }
+
+                if !self.ready_servers.lock().unwrap().contains(&server.name)
+                    && next_checks[index] <= now
+                {
+                    *attempt += 1;
+                    next_checks[index] = now + self.config.check_interval(server);
+                    pending.push((index, server, *attempt));
+                }
+            }
+
+            let workers = self
+                .config
+                .check_concurrency
+                .unwrap_or(pending.len())
+                .min(pending.len());
+            let queue = Mutex::new(pending.into_iter());
+            let mut results: Vec<_> = thread::scope(|scope| {
+                let workers: Vec<_> = (0..workers)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            let mut results = Vec::new();
+
+                            loop {
+                                let next = queue.lock().unwrap().next();
+                                let Some((index, server, attempt)) = next else {
+                                    break;
+                                };
+                                let result = check_server(
+                                    &self.config,
+                                    server,
+                                    &clients[index],
+                                    attempt,
+                                    max_attempts,
+                                    &self.events,
+                                );
+
+                                results.push((index, server, attempt, result));
+                            }
+
+                            results
+                        })
+                    })
+                    .collect();
+
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().expect("Health check panicked"))
+                    .collect()
+            });
+
+            results.sort_by_key(|(index, ..)| *index);
+
+            for (index, server, attempt, result) in results {
+                if let Some(status) = self.exit_status(index)? {
+                    return self.crashed(index, server, attempt, status);
+                }
+
+                match result {
+                    Ok(ServerStatus::Running) => {
+                        self.record_check(&server.name, attempt, "ready".to_string());
+
+                        if self
+                            .ready_servers
+                            .lock()
+                            .unwrap()
+                            .insert(server.name.to_string())
+                        {
+                            self.events.emit(Event::ServerReady {
+                                name: server.name.to_string(),
+                            });
+                        }
+                    }
+                    Ok(ServerStatus::Waiting) => {
+                        self.record_check(&server.name, attempt, "waiting".to_string());
+                    }
+                    Err(e) => return self.fail(index, server, attempt, e),
+                }
+            }
+
+            let next_check = {
+                let ready_servers = self.ready_servers.lock().unwrap();
+
+                self.config
+                    .servers
+                    .iter()
+                    .zip(&next_checks)
+                    .zip(&stopped)
+                    .filter(|((server, _), stopped)| {
+                        !**stopped && !ready_servers.contains(&server.name)
+                    })
+                    .map(|((_, next_check), _)| *next_check)
+                    .min()
+            };
+
+            if let Some(next_check) = next_check {
+                if let Some(deadline) = deadline {
+                    if next_check >= deadline {
+                        woken
+                            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                            .ok();
+
+                        if Instant::now() >= deadline {
+                            return Err(self.startup_timeout_error());
+                        }
+
+                        continue;
+                    }
+                }
+
+                woken
+                    .recv_timeout(next_check.saturating_duration_since(Instant::now()))
+                    .ok();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // Tasks that finished and servers that were stopped on purpose don't count as failed.
+    pub(crate) fn servers_running(&self) -> bool {
+        let stopped = self.stopped_servers();
+
+        self.config
+            .servers
+            .iter()
+            .zip(self.status())
+            .zip(stopped)
+            .all(|((server, state), stopped)| {
+                server.server_type == ServerType::Task || stopped || state.running
+            })
+    }
+
+    fn stopped_servers(&self) -> Vec<bool> {
+        self.server_processes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| p.stopped)
+            .collect()
+    }
+
+    fn exit_status(&self, index: usize) -> anyhow::Result<Option<ExitStatus>> {
+        Ok(self.server_processes.lock().unwrap()[index].try_wait()?)
+    }
+
+    fn crashed(
+        &self,
+        index: usize,
+        server: &Server,
+        attempt: u32,
+        status: ExitStatus,
+    ) -> anyhow::Result<()> {
+        self.events.emit(Event::ServerCrashed {
+            name: server.name.to_string(),
+            status,
+        });
+
+        self.fail(
+            index,
+            server,
+            attempt,
+            anyhow!("Server {} exited with {}", server.name, status),
+        )
+    }
+
+    pub fn verify_servers(&self) -> anyhow::Result<()> {
+        let stopped = self.stopped_servers();
+
+        for (index, server) in self.config.servers.iter().enumerate() {
+            if server.server_type == ServerType::Task || stopped[index] {
+                continue;
+            }
+
+            let client = HealthClient::new(&self.config, server, self.external)?;
+            let status = check_health(&self.config, server, &client).context(format!(
+                "Server {} is no longer ready before the command was started",
+                server.name
+            ))?;
+
+            if let Some(status) = self.exit_status(index)? {
+                bail!(
+                    "Server {} exited with {} before the command was started",
+                    server.name,
+                    status
+                );
+            }
+
+            if status == ServerStatus::Waiting {
+                bail!(
+                    "Server {} is no longer ready before the command was started",
+                    server.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn run_stages(&self, max_attempts: u32) -> anyhow::Result<()> {
+        for stage in &self.config.stages {
+            info!("Starting stage {}", stage.name);
+
+            for name in &stage.stop {
+                self.stop_one(name)?;
+            }
+
+            for name in &stage.restart {
+                self.restart(name)?;
+            }
+
+            self.wait_until_ready(max_attempts)?;
+
+            if let Some(command) = &stage.command {
+                self.verify_servers()?;
+                self.write_state_file()?;
+
+                let label = format!("{} (stage {})", command, stage.name);
+                let status = self.run_command_with(command, &label, None, Vec::new())?;
+
+                if !status.success() {
+                    bail!("Stage {} failed with {}", stage.name, status);
+                }
+            }
+        }
+
+        self.run_command()
+    }
+
+    pub fn run_command(&self) -> anyhow::Result<()> {
+        self.verify_servers()?;
+        self.write_state_file()?;
+
+        let _chaos = self
+            .config
+            .chaos
+            .is_some()
+            .then(|| ChaosMonkey::start(self.clone()));
+
+        if !self.config.commands.parallel.is_empty() {
+            return self.run_parallel_commands();
+        }
+
+        if self.config.command.is_empty() {
+            return Ok(());
+        }
+
+        if self.config.matrix.is_empty() {
+            let status = self.run_command_with(
+                &self.config.command,
+                &self.config.command,
+                None,
+                Vec::new(),
+            )?;
+
+            if !status.success() {
+                bail!("Command {} failed with {}", self.config.command, status);
+            }
+
+            return Ok(());
+        }
+
+        let mut results = Vec::new();
+
+        for entry in matrix_entries(&self.config.matrix) {
+            let label = format!(
+                "{} ({})",
+                self.config.command,
+                entry
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            let status = self.run_command_with(&self.config.command, &label, None, entry)?;
+
+            results.push((label, status));
+        }
+
+        let failed = print_results("Matrix results", &results);
+
+        if failed > 0 {
+            bail!(
+                "Command failed for {} of {} matrix entries",
+                failed,
+                results.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn run_parallel_commands(&self) -> anyhow::Result<()> {
+        let commands = &self.config.commands.parallel;
+        let results = thread::scope(|scope| {
+            let threads: Vec<_> = commands
+                .iter()
+                .map(|command| {
+                    scope.spawn(|| {
+                        self.run_command_with(
+                            &command.command,
+                            &command.command,
+                            Some(&command.name),
+                            Vec::new(),
+                        )
+                    })
+                })
+                .collect();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().expect("Command thread panicked"))
+                .collect::<Vec<_>>()
+        });
+        let results = commands
+            .iter()
+            .map(|command| command.name.to_string())
+            .zip(results)
+            .map(|(name, status)| status.map(|status| (name, status)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        console::flush();
+
+        let failed = print_results("Parallel command results", &results);
+
+        if failed > 0 {
+            bail!("{} of {} parallel commands failed", failed, results.len());
+        }
+
+        Ok(())
+    }
+
+    fn run_command_with(
+        &self,
+        command: &str,
+        label: &str,
+        prefix: Option<&str>,
+        vars: Vec<(String, String)>,
+    ) -> anyhow::Result<ExitStatus> {
+        let command = &self.expand_placeholders(command)?;
+        let mut env = self.command_env()?;
+
+        env.extend(vars);
+
+        // Parallel commands and matrix entries may share a label, the index tells them apart.
+        let index = self.command_count.fetch_add(1, Ordering::Relaxed);
+
+        self.events.emit(Event::CommandStarted {
+            command: label.to_string(),
+            index,
+        });
+
+        let (mut process, output) = run_command(command, prefix, &env, &self.output_settings)
+            .context(format!("Could not start process {}", command))?;
+        let pid = process.id();
+
+        info!("Running command {}", label);
+
+        self.running_commands
+            .lock()
+            .unwrap()
+            .push((label.to_string(), pid));
+
+        let status = process.wait();
+
+        self.running_commands
+            .lock()
+            .unwrap()
+            .retain(|(_, running)| *running != pid);
+
+        let status = status?;
+
+        if let Some(output) = &output {
+            output.wait_for_close(OUTPUT_CLOSE_TIMEOUT);
+
+            if !status.success() && self.output_settings.quiet_command {
+                print_tail(
+                    &format!("command {}", label),
+                    output,
+                    self.output_settings.tail_lines,
+                );
+            }
+        }
+
+        self.events.emit(Event::CommandExited {
+            command: label.to_string(),
+            index,
+            status,
+        });
+
+        Ok(status)
+    }
+
+    fn write_urls(&self) -> anyhow::Result<()> {
+        let urls: Vec<(&str, &str)> = self
+            .config
+            .servers
+            .iter()
+            .filter_map(|s| s.url.as_deref().map(|url| (s.name.as_str(), url)))
+            .collect();
+
+        if let Some(path) = &self.config.urls_file {
+            let json: serde_json::Map<String, serde_json::Value> = urls
+                .iter()
+                .map(|(name, url)| (name.to_string(), url.to_string().into()))
+                .collect();
+
+            fs::write(path, serde_json::to_string_pretty(&json)?)
+                .context(format!("Could not write urls file {}", path.display()))?;
+        }
+
+        if self.config.github_output {
+            if let Some(path) = env::var_os("GITHUB_OUTPUT") {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .context("Could not open GITHUB_OUTPUT")?;
+
+                for (name, url) in &urls {
+                    writeln!(file, "{}_url={}", env_key(name).to_lowercase(), url)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn command_env(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let mut env = Vec::new();
+
+        for state in self.status() {
+            let key = env_key(&state.name);
+
+            if let Some(url) = &state.url {
+                env.push((format!("SERVER_{}_URL", key), url.to_string()));
+
+                if let Some(port) = port(url) {
+                    env.push((format!("SERVER_{}_PORT", key), port.to_string()));
+                }
+            }
+
+            env.push((format!("SERVER_{}_PID", key), state.pid.to_string()));
+        }
+
+        if let Some(base_url) = self.config.base_url() {
+            env.push(("PLAYWRIGHT_BASE_URL".to_string(), base_url.to_string()));
+            env.push(("CYPRESS_BASE_URL".to_string(), base_url));
+        }
+
+        if let Some(state_dir) = &*self.state_dir.lock().unwrap() {
+            env.push((
+                "SERVER_RUNNER_STATE_FILE".to_string(),
+                state_dir.path().join(STATE_FILE).display().to_string(),
+            ));
+        }
+
+        Ok(env)
+    }
+
+    fn write_state_file(&self) -> anyhow::Result<()> {
+        let state = serde_json::to_string_pretty(&self.status())?;
+        let mut state_dir = self.state_dir.lock().unwrap();
+        let dir = match state_dir.take() {
+            Some(dir) => dir,
+            None => create_state_dir()?,
+        };
+        let dir = state_dir.insert(dir).path();
+        let path = dir.join(STATE_FILE);
+        let mut file = tempfile::NamedTempFile::new_in(dir)
+            .context(format!("Could not write state file {}", path.display()))?;
+
+        file.write_all(state.as_bytes())
+            .context(format!("Could not write state file {}", path.display()))?;
+        file.persist(&path)
+            .context(format!("Could not write state file {}", path.display()))?;
+
+        Ok(())
+    }
+
+    fn expand_placeholders(&self, command: &str) -> anyhow::Result<String> {
+        let states = self.status();
+        let placeholder = Regex::new(r"\{(url|port|pid):([^}]+)\}")?;
+        let mut error = None;
+        let expanded = placeholder.replace_all(command, |captures: &regex::Captures| {
+            let name = &captures[2];
+            let value = states
+                .iter()
+                .find(|state| env_key(&state.name) == env_key(name))
+                .and_then(|state| match &captures[1] {
+                    "url" => state.url.clone(),
+                    "port" => state.url.as_deref().and_then(port).map(|p| p.to_string()),
+                    _ => Some(state.pid.to_string()),
+                });
+
+            value.unwrap_or_else(|| {
+                error.get_or_insert(anyhow!(
+                    "Could not resolve {} in command {}",
+                    &captures[0],
+                    command
+                ));
+                String::new()
+            })
+        });
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(expanded.to_string()),
+        }
+    }
+
+    fn startup_timeout_error(&self) -> anyhow::Error {
+        let ready_servers = self.ready_servers.lock().unwrap().clone();
+        let pending: Vec<&Server> = self
+            .config
+            .servers
+            .iter()
+            .filter(|s| !ready_servers.contains(&s.name))
+            .collect();
+        let results: Vec<String> = {
+            let health = self.health.lock().unwrap();
+
+            pending
+                .iter()
+                .map(
+                    |s| match health.get(&s.name).and_then(|h| h.history.last(1).pop()) {
+                        Some(check) => format!("{} (last check: {})", s.name, check.result),
+                        None => format!("{} (not checked yet)", s.name),
+                    },
+                )
+                .collect()
+        };
+
+        for server in pending {
+            self.events.emit(Event::ReadinessFailed {
+                name: server.name.to_string(),
+                error: "startup timeout exceeded".to_string(),
+            });
+        }
+
+        anyhow!(
+            "Servers were not ready after {:?}: {}",
+            self.config.startup_timeout.unwrap_or_default(),
+            results.join(", ")
+        )
+    }
+
+    fn fail(
+        &self,
+        index: usize,
+        server: &Server,
+        attempt: u32,
+        error: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        self.record_check(&server.name, attempt, format!("{:#}", error));
+
+        print_output_tail(
+            &mut self.server_processes.lock().unwrap()[index],
+            self.output_settings.tail_lines,
+        );
+
+        self.events.emit(Event::ReadinessFailed {
+            name: server.name.to_string(),
+            error: format!("{:#}", error),
+        });
+
+        Err(error)
+    }
+
+    fn record_check(&self, name: &str, attempt: u32, result: String) {
+        let mut health = self.health.lock().unwrap();
+        let health = health.entry(name.to_string()).or_insert_with(|| Health {
+            attempts: 0,
+            history: RingBuffer::new(HEALTH_HISTORY_LENGTH),
+        });
+
+        health.attempts = attempt;
+        health.history.push(CheckRecord {
+            time: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            attempt,
+            result,
+        });
+    }
+
+    pub fn snapshot(&self) -> Vec<ServerSnapshot> {
+        let states = self.status();
+        let health = self.health.lock().unwrap();
+
+        states
+            .into_iter()
+            .map(|state| {
+                let (attempts, history) = match health.get(&state.name) {
+                    Some(h) => (h.attempts, h.history.last(HEALTH_HISTORY_LENGTH)),
+                    None => (0, Vec::new()),
+                };
+
+                ServerSnapshot {
+                    state,
+                    attempts,
+                    history,
+                }
+            })
+            .collect()
+    }
+
+    pub fn status(&self) -> Vec<ServerState> {
+        let ready_servers = self.ready_servers.lock().unwrap().clone();
+        let mut server_processes = self.server_processes.lock().unwrap();
+
+        self.config
+            .servers
+            .iter()
+            .zip(server_processes.iter_mut())
+            .map(|(server, p)| ServerState {
+                name: server.name.to_string(),
+                url: server.url.clone(),
+                pid: p.pid(),
+                running: !p.stopped && matches!(p.try_wait(), Ok(None)),
+                ready: ready_servers.contains(&server.name),
+            })
+            .collect()
+    }
+
+    pub fn restart(&self, name: &str) -> anyhow::Result<()> {
+        let index = self.server_index(name)?;
+        let server = &self.config.servers[index];
+
+        info!("Restarting server {}", name);
+
+        stop_server(server, &mut self.server_processes.lock().unwrap()[index])?;
+
+        if server.server_type == ServerType::Compose {
+            self.config.compose.stop_service(server.service())?;
+        }
+
+        self.ready_servers.lock().unwrap().remove(name);
+
+        self.events.emit(Event::ServerStarting {
+            name: name.to_string(),
+        });
+
+        if server.server_type == ServerType::Compose {
+            self.config.compose.start_service(server.service())?;
+        }
+
+        let mut replacement = spawn_server(&self.config, server, &self.output_settings)?;
+        let pid = replacement.pid();
+
+        let replaced = {
+            let mut server_processes = self.server_processes.lock().unwrap();
+            let server_process = &mut server_processes[index];
+
+            // Another restart or a teardown may have happened while the server was starting.
+            if server_process.stopped && !self.stopping.load(Ordering::SeqCst) {
+                std::mem::swap(server_process, &mut replacement);
+                self.watch_exit(index, server_process);
+
+                true
+            } else {
+                false
+            }
+        };
+
+        if !replaced {
+            stop_server(server, &mut replacement)?;
+            bail!("Server {} was stopped while it was restarting", name);
+        }
+
+        self.events.emit(Event::ServerSpawned {
+            name: name.to_string(),
+            pid,
+        });
+
+        Ok(())
+    }
+
+    pub fn stop_one(&self, name: &str) -> anyhow::Result<()> {
+        let index = self.server_index(name)?;
+
+        info!("Stopping server {}", name);
+
+        let server = &self.config.servers[index];
+
+        stop_server(server, &mut self.server_processes.lock().unwrap()[index])?;
+
+        // The tracked process of a compose service only follows its logs.
+        if server.server_type == ServerType::Compose {
+            self.config.compose.stop_service(server.service())?;
+        }
+
+        self.ready_servers.lock().unwrap().remove(name);
+
+        Ok(())
+    }
+
+    pub fn logs(&self, name: &str, count: usize) -> anyhow::Result<Vec<String>> {
+        let index = self.server_index(name)?;
+        let server_processes = self.server_processes.lock().unwrap();
+
+        match &server_processes[index].output {
+            Some(output) => Ok(output.lines(count)),
+            None => bail!("Output of server {} is not captured", name),
+        }
+    }
+
+    fn server_index(&self, name: &str) -> anyhow::Result<usize> {
+        self.config
+            .servers
+            .iter()
+            .position(|s| s.name == name)
+            .context(format!("Unknown server {}", name))
+    }
+
+    // Teardown also runs after a panicking event handler, so it ignores poisoned locks.
+    fn running_commands(&self) -> Vec<(String, u32)> {
+        self.running_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    #[cfg(unix)]
+    fn stop_commands(&self) {
+        let commands = self.running_commands();
+
+        for (label, pid) in &commands {
+            info!("Stopping command {}", label);
+
+            signal_command(*pid, libc::SIGTERM);
+        }
+
+        let deadline = Instant::now() + COMMAND_STOP_TIMEOUT;
+
+        while !self.running_commands().is_empty() && Instant::now() < deadline {
+            thread::sleep(COMMAND_STOP_INTERVAL);
+        }
+
+        let running = self.running_commands();
+
+        for (label, pid) in commands {
+            let is_running = running.iter().any(|(_, running)| *running == pid);
+
+            if is_running {
+                warn!("Command {} did not stop in time, killing it", label);
+            }
+
+            // Children of a detached command may outlive it, so its group is always killed.
+            if is_running || detaches_commands() {
+                signal_command(pid, libc::SIGKILL);
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    fn stop_commands(&self) {
+        for (label, pid) in self.running_commands() {
+            info!("Stopping command {}", label);
+
+            kill_process_tree(pid);
+        }
+    }
+
+    pub fn stop(&self) -> anyhow::Result<()> {
+        self.stopping.store(true, Ordering::SeqCst);
+
+        if let Some(gate) = self
+            .readiness_gate
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            gate.close();
+        }
+
+        self.stop_commands();
+
+        self.state_dir
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        let mut server_processes = self
+            .server_processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut result = stop_servers(&self.config.servers, &mut server_processes);
+
+        if self.config.uses_compose() {
+            result = result.and(self.config.compose.down());
+        }
+
+        console::flush();
+
+        result
+    }
+}
+
+pub fn wait_for_external_servers(config: Config, max_attempts: u32) -> anyhow::Result<()> {
+    ServerManager::external(config).wait_until_ready(max_attempts)
+}
+
+fn print_results(title: &str, results: &[(String, ExitStatus)]) -> usize {
+    eprintln!("----- {} -----", title);
+
+    for (label, status) in results {
+        eprintln!("{}: {}", label, status);
+    }
+
+    results
+        .iter()
+        .filter(|(_, status)| !status.success())
+        .count()
+}
+
+fn matrix_entries(matrix: &BTreeMap<String, Vec<String>>) -> Vec<Vec<(String, String)>> {
+    matrix
+        .iter()
+        .fold(vec![Vec::new()], |entries, (key, values)| {
+            entries
+                .iter()
+                .flat_map(|entry| {
+                    values.iter().map(move |value| {
+                        let mut entry = entry.clone();
+
+                        entry.push((key.to_string(), value.to_string()));
+                        entry
+                    })
+                })
+                .collect()
+        })
+}
+
+fn port(url: &str) -> Option<u16> {
+    reqwest::Url::parse(url).ok()?.port_or_known_default()
+}
+
+fn create_state_dir() -> anyhow::Result<TempDir> {
+    let mut builder = tempfile::Builder::new();
+
+    builder.prefix("server-runner-");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+
+    builder
+        .tempdir()
+        .context("Could not create a directory for the state file")
+}
+
+fn env_key(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn start_servers(
+    config: &Config,
+    output_settings: &OutputSettings,
+    events: &Events,
+) -> anyhow::Result<Vec<ServerProcess>> {
+    let mut server_processes = Vec::with_capacity(config.servers.len());
+
+    for s in &config.servers {
+        info!("Starting server {}", s.name);
+
+        events.emit(Event::ServerStarting {
+            name: s.name.to_string(),
+        });
+
+        let server_process = match spawn_server(config, s, output_settings) {
+            Ok(server_process) => server_process,
+            Err(e) => {
+                if let Err(stop_error) = stop_servers(&config.servers, &mut server_processes) {
+                    warn!("Could not stop servers: {:#}", stop_error);
+                }
+
+                return Err(e.context(format!("Could not start server {}", s.name)));
+            }
+        };
+
+        events.emit(Event::ServerSpawned {
+            name: s.name.to_string(),
+            pid: server_process.pid(),
+        });
+
+        server_processes.push(server_process);
+
+        if s.server_type == ServerType::Task {
+            if let Err(e) = wait_for_task(s, server_processes.last_mut().unwrap(), output_settings)
+            {
+                events.emit(Event::ReadinessFailed {
+                    name: s.name.to_string(),
+                    error: format!("{:#}", e),
+                });
+
+                if let Err(stop_error) = stop_servers(&config.servers, &mut server_processes) {
+                    warn!("Could not stop servers: {:#}", stop_error);
+                }
+
+                return Err(e);
+            }
+
+            events.emit(Event::ServerReady {
+                name: s.name.to_string(),
+            });
+        }
+    }
+
+    Ok(server_processes)
+}
+
+fn spawn_server(
+    config: &Config,
+    server: &Server,
+    output_settings: &OutputSettings,
+) -> anyhow::Result<ServerProcess> {
+    let in_process = match server.server_type {
+        ServerType::Static => static_files::start(server)?,
+        ServerType::Mock => mock::start(server)?,
+        _ => {
+            let (process, output) = run_server(config, server, output_settings)?;
+
+            return Ok(ServerProcess {
+                name: server.name.to_string(),
+                process: Some(process),
+                in_process: None,
+                output,
+                stopped: false,
+            });
+        }
+    };
+
+    Ok(ServerProcess {
+        name: server.name.to_string(),
+        process: None,
+        in_process: Some(in_process),
+        output: None,
+        stopped: false,
+    })
+}
+
+fn wait_for_task(
+    server: &Server,
+    server_process: &mut ServerProcess,
+    output_settings: &OutputSettings,
+) -> anyhow::Result<()> {
+    info!("Waiting for task {} to finish", server.name);
+
+    let status = match &mut server_process.process {
+        Some(process) => process.wait(),
+        None => return Ok(()),
+    }
+    .context(format!("Could not wait for task {}", server.name))?;
+
+    if !status.success() {
+        print_output_tail(server_process, output_settings.tail_lines);
+        bail!("Task {} failed with {}", server.name, status);
+    }
+
+    info!("Task {} finished", server.name);
+
+    Ok(())
+}
+
+fn stop_servers(servers: &[Server], server_processes: &mut [ServerProcess]) -> anyhow::Result<()> {
+    let mut result = Ok(());
+
+    for (server, p) in servers.iter().zip(server_processes.iter_mut()) {
+        info!("Stopping server {}", p.name);
+
+        result = result.and(stop_server(server, p));
+    }
+
+    result
+}
+
+fn stop_server(server: &Server, server_process: &mut ServerProcess) -> anyhow::Result<()> {
+    server_process.stopped = true;
+
+    if let Some(mut in_process) = server_process.in_process.take() {
+        in_process.stop();
+    }
+
+    let Some(process) = &mut server_process.process else {
+        return Ok(());
+    };
+
+    if process.try_wait()?.is_none() {
+        // The local process is killed below even if the container or remote process could not
+        // be stopped, so it is never left behind.
+        let stopped = match (&server.server_type, &server.host) {
+            (ServerType::Docker, _) => docker::stop(server),
+            (ServerType::Command, Some(host)) => ssh::stop(server, host),
+            #[cfg(unix)]
+            (ServerType::NpmScript, _) => {
+                kill_process_group(process.id());
+                Ok(())
+            }
+            #[cfg(windows)]
+            _ => {
+                kill_process_tree(process.id());
+                Ok(())
+            }
+            #[cfg(not(windows))]
+            _ => Ok(()),
+        };
+
+        if let Err(e) = stopped {
+            warn!("{:#}", e);
+        }
+    }
+
+    process
+        .kill()
+        .context(format!("Failed to stop process {}", server_process.name))?;
+    process.wait()?;
+
+    if let Some(output) = &server_process.output {
+        if !output.join(OUTPUT_CLOSE_TIMEOUT) {
+            warn!(
+                "Output of server {} is still open, probably held by a child process",
+                server_process.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_output_tail(server_process: &mut ServerProcess, count: usize) {
+    let exited = matches!(server_process.try_wait(), Ok(Some(_)));
+    let output = match &server_process.output {
+        Some(output) => output,
+        None => return,
+    };
+
+    if exited {
+        output.wait_for_close(OUTPUT_CLOSE_TIMEOUT);
+    }
+
+    print_tail(&format!("server {}", server_process.name), output, count);
+}
+
+fn print_tail(source: &str, output: &OutputTail, count: usize) {
+    let lines = output.lines(count);
+
+    if lines.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "----- Last {} lines of output from {} -----",
+        lines.len(),
+        source
+    );
+
+    for line in lines {
+        eprintln!("{}", line);
+    }
+
+    eprintln!("----- End of output from {} -----", source);
+}
+
+fn check_server(
+    config: &Config,
+    server: &Server,
+    client: &HealthClient,
+    attempts: u32,
+    max_attempts: u32,
+    events: &Events,
+) -> anyhow::Result<ServerStatus> {
+    let server_name = &server.name;
+
+    events.emit(Event::CheckAttempt {
+        name: server_name.to_string(),
+        url: server.url.clone(),
+        attempt: attempts,
+    });
+
+    let status = check_health(config, server, client)?;
+
+    events.emit(Event::CheckFinished {
+        name: server_name.to_string(),
+        attempt: attempts,
+        ready: status == ServerStatus::Running,
+    });
+
+    if gives_up(&status, attempts, max_attempts) {
+        bail!(
+            "Could not connect to server {} after {} attempts",
+            server_name,
+            attempts
+        );
+    }
+
+    Ok(status)
+}
+
+fn gives_up(status: &ServerStatus, attempt: u32, max_attempts: u32) -> bool {
+    *status == ServerStatus::Waiting && attempt >= max_attempts
+}
+
+fn check_health(
+    config: &Config,
+    server: &Server,
+    client: &HealthClient,
+) -> anyhow::Result<ServerStatus> {
+    let healthy = match server.server_type {
+        ServerType::Command
+        | ServerType::NpmScript
+        | ServerType::Cargo
+        | ServerType::Task
+        | ServerType::Static
+        | ServerType::Mock => true,
+        ServerType::Docker => docker::is_healthy(&client.container)?,
+        ServerType::Compose => config.compose.is_healthy(server.service())?,
+    };
+
+    if !healthy {
+        return Ok(ServerStatus::Waiting);
+    }
+
+    match (&server.url, &server.check) {
+        (Some(url), CheckType::Http) => check_url(client, &server.name, url),
+        (Some(url), CheckType::Tcp) => check_tcp(client, &server.name, url),
+        (None, _) => Ok(ServerStatus::Running),
+    }
+}
+
+fn check_tcp(client: &HealthClient, server_name: &str, url: &str) -> anyhow::Result<ServerStatus> {
+    let parsed = reqwest::Url::parse(url)?;
+    let host = parsed.host_str().unwrap_or_default();
+    let port = parsed
+        .port_or_known_default()
+        .context(format!("Server {} has no port in url {}", server_name, url))?;
+    let Ok(addresses) = (host.trim_matches(['[', ']']), port).to_socket_addrs() else {
+        return Ok(ServerStatus::Waiting);
+    };
+    let timeout = client.timeout.unwrap_or(TCP_CONNECT_TIMEOUT);
+    let connected = addresses
+        .into_iter()
+        .any(|address| TcpStream::connect_timeout(&address, timeout).is_ok());
+
+    if connected {
+        Ok(ServerStatus::Running)
+    } else {
+        Ok(ServerStatus::Waiting)
+    }
+}
+
+fn check_url(client: &HealthClient, server_name: &str, url: &str) -> anyhow::Result<ServerStatus> {
+    let mut response = if client.head.load(Ordering::Relaxed) {
+        client.client.head(url).send()
+    } else {
+        client.client.get(url).send()
+    };
+
+    if let Ok(head_response) = &response {
+        if matches!(
+            head_response.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) && client.head.swap(false, Ordering::Relaxed)
+        {
+            info!(
+                "Server {} does not support HEAD requests, falling back to GET",
+                server_name
+            );
+            response = client.client.get(url).send();
+        }
+    }
+
+    match response {
+        Ok(response) if response.status().is_success() => Ok(ServerStatus::Running),
+        Ok(_) => Ok(ServerStatus::Waiting),
+        Err(error) => {
+            if error.is_connect() || error.is_timeout() || error.is_request() {
+                Ok(ServerStatus::Waiting)
+            } else {
+                bail!("Could not connect to server {} on url {}", server_name, url);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_checking_until_the_last_attempt() {
+        assert!(!gives_up(&ServerStatus::Waiting, 1, 3));
+        assert!(!gives_up(&ServerStatus::Waiting, 2, 3));
+        assert!(gives_up(&ServerStatus::Waiting, 3, 3));
+    }
+
+    #[test]
+    fn accepts_a_server_ready_on_the_last_attempt() {
+        assert!(!gives_up(&ServerStatus::Running, 3, 3));
+    }
+
+    #[test]
+    fn counts_attempts_beyond_u8() {
+        assert!(!gives_up(&ServerStatus::Waiting, 256, 300));
+        assert!(gives_up(&ServerStatus::Waiting, 300, 300));
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{bail, Context};
+use log::info;
+use std::process::{Command, Stdio};
+
+use crate::config::Server;
+
+fn pid_file(server: &Server) -> String {
+    let name: String = server
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    format!("/tmp/server-runner-{}-{}.pid", name, std::process::id())
+}
+
+pub(crate) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+pub fn run_command(server: &Server, host: &str) -> Command {
+    let script = format!("echo $$ > {}; exec {}", pid_file(server), server.command);
+    let mut cmd = Command::new("ssh");
+
+    for forward in &server.forward {
+        cmd.args(["-L", forward]);
+    }
+
+    cmd.args([host, &format!("sh -c {}", quote(&script))]);
+
+    cmd
+}
+
+pub fn stop(server: &Server, host: &str) -> anyhow::Result<()> {
+    let pid_file = pid_file(server);
+
+    info!(
+        "Stopping remote process of server {} on {}",
+        server.name, host
+    );
+
+    let status = Command::new("ssh")
+        .args([
+            host,
+            &format!(
+                "kill $(cat {pid_file}) 2>/dev/null; rm -f {pid_file}",
+                pid_file = pid_file
+            ),
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context(format!("Could not connect to {}", host))?;
+
+    if !status.success() {
+        bail!(
+            "Could not stop remote process of server {} on {}",
+            server.name,
+            host
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Context};
+use log::info;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::config::Server;
+use crate::in_process::InProcessServer;
+
+const PROGRAM: &str = "server-runner";
+const INDEX_FILE: &str = "index.html";
+
+pub(crate) fn program() -> PathBuf {
+    env::current_exe()
+        .ok()
+        .filter(|exe| exe.file_stem().is_some_and(|stem| stem == PROGRAM))
+        .unwrap_or(PathBuf::from(PROGRAM))
+}
+
+pub(crate) fn port(server: &Server) -> anyhow::Result<u16> {
+    server
+        .url
+        .as_deref()
+        .and_then(|url| reqwest::Url::parse(url).ok())
+        .and_then(|url| url.port_or_known_default())
+        .context(format!("Server {} has no port in its url", server.name))
+}
+
+fn dir(server: &Server) -> anyhow::Result<&Path> {
+    server
+        .dir
+        .as_deref()
+        .context(format!("Server {} has no dir", server.name))
+}
+
+pub fn run_command(server: &Server) -> anyhow::Result<Command> {
+    let mut cmd = Command::new(program());
+
+    cmd.arg("serve-static")
+        .arg(dir(server)?)
+        .args(["--port", &port(server)?.to_string()]);
+
+    if server.spa {
+        cmd.arg("--spa");
+    }
+
+    Ok(cmd)
+}
+
+pub fn start(server: &Server) -> anyhow::Result<InProcessServer> {
+    serve(dir(server)?, port(server)?, server.spa)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn resolve(dir: &Path, url: &str, spa: bool) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let relative = Path::new(path.trim_start_matches('/'));
+
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let mut file = dir.join(relative);
+
+    if file.is_dir() {
+        file = file.join(INDEX_FILE);
+    }
+
+    if file.is_file() {
+        Some(file)
+    } else if spa {
+        Some(dir.join(INDEX_FILE)).filter(|index| index.is_file())
+    } else {
+        None
+    }
+}
+
+fn respond(request: Request, dir: &Path, spa: bool) {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        request.respond(Response::empty(405)).ok();
+        return;
+    }
+
+    let response = match resolve(dir, request.url(), spa).map(|file| (fs::read(&file), file)) {
+        Some((Ok(contents), file)) => {
+            let header = Header::from_bytes("Content-Type", content_type(&file))
+                .expect("Content type header is valid");
+
+            Response::from_data(contents).with_header(header)
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+
+    request.respond(response).ok();
+}
+
+pub fn serve(dir: &Path, port: u16, spa: bool) -> anyhow::Result<InProcessServer> {
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("Could not serve {} on port {}: {}", dir.display(), port, e))?;
+    let dir = dir.to_path_buf();
+
+    info!("Serving {} on http://127.0.0.1:{}", dir.display(), port);
+
+    Ok(InProcessServer::start(server, move |request| {
+        respond(request, &dir, spa)
+    }))
+}
@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+
+const SYSLOG_SOCKET: &str = "/dev/log";
+const FACILITY_USER: u8 = 1;
+
+pub const SEVERITY_ERROR: u8 = 3;
+pub const SEVERITY_INFO: u8 = 6;
+
+pub struct Syslog {
+    identifier: String,
+    pid: u32,
+    socket: UnixDatagram,
+    buffer: Vec<u8>,
+}
+
+impl Syslog {
+    pub fn connect(identifier: &str, pid: u32) -> io::Result<Syslog> {
+        let socket = UnixDatagram::unbound()?;
+
+        socket.connect(SYSLOG_SOCKET)?;
+
+        Ok(Syslog {
+            identifier: identifier.to_string(),
+            pid,
+            socket,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn send(&self, severity: u8, message: &[u8]) -> io::Result<()> {
+        let message = String::from_utf8_lossy(message);
+        let packet = format!(
+            "<{}>{}[{}]: {}",
+            FACILITY_USER * 8 + severity,
+            self.identifier,
+            self.pid,
+            message.trim_end_matches(['\r', '\n'])
+        );
+
+        self.socket.send(packet.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl Write for Syslog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        while let Some(index) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=index).collect();
+
+            self.send(SEVERITY_INFO, &line)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let line: Vec<u8> = self.buffer.drain(..).collect();
+
+            self.send(SEVERITY_INFO, &line)?;
+        }
+
+        Ok(())
+    }
+}
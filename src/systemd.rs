@@ -0,0 +1,109 @@
+use log::warn;
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, IntoRawFd, RawFd};
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram, UnixListener};
+use std::thread;
+use std::time::Duration;
+
+use crate::server_management::ServerManager;
+
+const LISTEN_FDS_START: RawFd = 3;
+
+pub enum ListenSocket {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+pub fn notify(state: &str) -> io::Result<bool> {
+    let path = match env::var("NOTIFY_SOCKET") {
+        Ok(path) if !path.is_empty() => path,
+        _ => return Ok(false),
+    };
+    let address = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return Ok(false),
+        None => SocketAddr::from_pathname(&path)?,
+    };
+    let socket = UnixDatagram::unbound()?;
+
+    socket.send_to_addr(state.as_bytes(), &address)?;
+
+    Ok(true)
+}
+
+// Takes the sockets passed by a socket unit, so the servers don't inherit them.
+pub fn listen_sockets() -> Vec<ListenSocket> {
+    let for_us = env::var("LISTEN_PID").is_ok_and(|pid| pid.parse() == Ok(std::process::id()));
+    let count: RawFd = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0);
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    if !for_us {
+        return Vec::new();
+    }
+
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .filter_map(|fd| {
+            // Safe as systemd passes these descriptors to us and nothing else owns them.
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+
+            let listener = unsafe { UnixListener::from_raw_fd(fd) };
+
+            if listener.local_addr().is_ok() {
+                return Some(ListenSocket::Unix(listener));
+            }
+
+            let listener = unsafe { TcpListener::from_raw_fd(listener.into_raw_fd()) };
+
+            if listener.local_addr().is_ok() {
+                return Some(ListenSocket::Tcp(listener));
+            }
+
+            warn!(
+                "Ignoring socket {} passed by systemd, it is neither TCP nor Unix",
+                fd
+            );
+            None
+        })
+        .collect()
+}
+
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+
+    Some(Duration::from_micros(usec / 2))
+}
+
+pub fn start_watchdog(server_manager: ServerManager) {
+    let interval = match watchdog_interval() {
+        Some(interval) => interval,
+        None => return,
+    };
+
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+
+        if server_manager.servers_running() {
+            if let Err(e) = notify("WATCHDOG=1") {
+                warn!("Could not send watchdog notification to systemd: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,350 @@
+use anyhow::Context;
+use log::info;
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::events::Event;
+
+const DEFAULT_SERVICE_NAME: &str = "server-runner";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+struct Span {
+    id: String,
+    parent_id: Option<String>,
+    name: String,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    attributes: Vec<(String, Value)>,
+    status: Option<u8>,
+}
+
+#[derive(Default)]
+struct Spans {
+    finished: Vec<Span>,
+    open: HashMap<String, Span>,
+}
+
+pub struct Tracer {
+    endpoint: String,
+    headers: Vec<(String, String)>,
+    service_name: String,
+    trace_id: String,
+    root_id: String,
+    started: SystemTime,
+    spans: Mutex<Spans>,
+}
+
+impl Tracer {
+    pub fn from_env() -> Option<Tracer> {
+        if env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true")) {
+            return None;
+        }
+
+        let endpoint = match env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            Ok(endpoint) => endpoint,
+            Err(_) => format!(
+                "{}/v1/traces",
+                env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .ok()?
+                    .trim_end_matches('/')
+            ),
+        };
+        let headers = env::var("OTEL_EXPORTER_OTLP_TRACES_HEADERS")
+            .or_else(|_| env::var("OTEL_EXPORTER_OTLP_HEADERS"))
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|header| header.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        Some(Tracer {
+            endpoint,
+            headers,
+            service_name: env::var("OTEL_SERVICE_NAME").unwrap_or(DEFAULT_SERVICE_NAME.to_string()),
+            trace_id: format!("{:016x}{:016x}", random_id(), random_id()),
+            root_id: format!("{:016x}", random_id()),
+            started: SystemTime::now(),
+            spans: Mutex::new(Spans::default()),
+        })
+    }
+
+    pub fn handle(&self, event: &Event) {
+        let mut spans = self.spans.lock().unwrap();
+
+        match event {
+            Event::ServerStarting { name } => self.open(
+                &mut spans,
+                format!("spawn:{}", name),
+                format!("spawn {}", name),
+                None,
+            ),
+            Event::ServerSpawned { name, pid } => {
+                spans.close(&format!("spawn:{}", name), STATUS_OK, vec![]);
+                spans.close(&format!("wait:{}", name), STATUS_ERROR, vec![]);
+                self.open(
+                    &mut spans,
+                    format!("wait:{}", name),
+                    format!("wait {}", name),
+                    None,
+                );
+                spans.add_attribute(&format!("wait:{}", name), "process.pid", json!(pid));
+            }
+            Event::CheckAttempt { name, url, attempt } => {
+                let parent = spans
+                    .open
+                    .get(&format!("wait:{}", name))
+                    .map(|s| s.id.clone());
+
+                self.open(
+                    &mut spans,
+                    format!("check:{}", name),
+                    format!("check {}", name),
+                    parent,
+                );
+                spans.add_attribute(&format!("check:{}", name), "url.full", json!(url));
+                spans.add_attribute(&format!("check:{}", name), "attempt", json!(attempt));
+            }
+            Event::CheckFinished { name, ready, .. } => spans.close(
+                &format!("check:{}", name),
+                STATUS_OK,
+                vec![("ready".to_string(), json!(ready))],
+            ),
+            Event::ServerReady { name } => {
+                spans.close(&format!("wait:{}", name), STATUS_OK, vec![])
+            }
+            Event::ServerCrashed { name, status } => spans.close(
+                &format!("wait:{}", name),
+                STATUS_ERROR,
+                vec![("exit_status".to_string(), json!(status.to_string()))],
+            ),
+            Event::CommandStarted { command, index } => {
+                self.open(
+                    &mut spans,
+                    format!("command:{}", index),
+                    format!("command {}", command),
+                    None,
+                );
+                spans.add_attribute(
+                    &format!("command:{}", index),
+                    "process.command_line",
+                    json!(command),
+                );
+            }
+            Event::CommandExited { index, status, .. } => spans.close(
+                &format!("command:{}", index),
+                if status.success() {
+                    STATUS_OK
+                } else {
+                    STATUS_ERROR
+                },
+                vec![("process.exit.code".to_string(), json!(status.code()))],
+            ),
+            Event::ServersReady | Event::ReadinessFailed { .. } => {}
+        }
+    }
+
+    pub fn export(&self) -> anyhow::Result<()> {
+        let mut spans = self.spans.lock().unwrap();
+        let now = SystemTime::now();
+        let open: Vec<String> = spans.open.keys().cloned().collect();
+
+        for key in open {
+            spans.close(&key, STATUS_ERROR, vec![]);
+        }
+
+        let mut exported: Vec<Value> = spans
+            .finished
+            .drain(..)
+            .map(|span| self.to_json(span, now))
+            .collect();
+
+        exported.push(self.to_json(
+            Span {
+                id: self.root_id.clone(),
+                parent_id: None,
+                name: "run".to_string(),
+                start: self.started,
+                end: Some(now),
+                attributes: vec![],
+                status: None,
+            },
+            now,
+        ));
+
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", json!(self.service_name))]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": DEFAULT_SERVICE_NAME },
+                    "spans": exported
+                }]
+            }]
+        });
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(EXPORT_TIMEOUT)
+            .build()?;
+        let mut request = client
+            .post(&self.endpoint)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+
+        request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .context(format!("Could not export traces to {}", self.endpoint))?;
+
+        info!("Exported traces to {}", self.endpoint);
+
+        Ok(())
+    }
+
+    fn open(&self, spans: &mut Spans, key: String, name: String, parent_id: Option<String>) {
+        spans.open.insert(
+            key,
+            Span {
+                id: format!("{:016x}", random_id()),
+                parent_id: Some(parent_id.unwrap_or(self.root_id.clone())),
+                name,
+                start: SystemTime::now(),
+                end: None,
+                attributes: vec![],
+                status: None,
+            },
+        );
+    }
+
+    fn to_json(&self, span: Span, now: SystemTime) -> Value {
+        let mut value = json!({
+            "traceId": self.trace_id,
+            "spanId": span.id,
+            "name": span.name,
+            "kind": 1,
+            "startTimeUnixNano": unix_nanos(span.start),
+            "endTimeUnixNano": unix_nanos(span.end.unwrap_or(now)),
+            "attributes": span
+                .attributes
+                .into_iter()
+                .map(|(key, value)| attribute(&key, value))
+                .collect::<Vec<Value>>(),
+        });
+
+        if let Some(parent_id) = span.parent_id {
+            value["parentSpanId"] = json!(parent_id);
+        }
+
+        if let Some(status) = span.status {
+            value["status"] = json!({ "code": status });
+        }
+
+        value
+    }
+}
+
+impl Spans {
+    fn add_attribute(&mut self, key: &str, name: &str, value: Value) {
+        if let Some(span) = self.open.get_mut(key) {
+            span.attributes.push((name.to_string(), value));
+        }
+    }
+
+    fn close(&mut self, key: &str, status: u8, attributes: Vec<(String, Value)>) {
+        if let Some(mut span) = self.open.remove(key) {
+            span.end = Some(SystemTime::now());
+            span.status = Some(status);
+            span.attributes.extend(attributes);
+
+            self.finished.push(span);
+        }
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    let value = match value {
+        Value::Bool(value) => json!({ "boolValue": value }),
+        Value::Number(value) => json!({ "intValue": value.to_string() }),
+        Value::Null => json!({ "stringValue": "" }),
+        value => {
+            json!({ "stringValue": value.as_str().map(str::to_string).unwrap_or(value.to_string()) })
+        }
+    };
+
+    json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn random_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::ExitStatus;
+
+    #[test]
+    fn keeps_spans_of_commands_with_the_same_label() {
+        let tracer = Tracer {
+            endpoint: String::new(),
+            headers: vec![],
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+            trace_id: String::new(),
+            root_id: String::new(),
+            started: SystemTime::now(),
+            spans: Mutex::new(Spans::default()),
+        };
+        let command = "npm test".to_string();
+
+        for index in 0..2 {
+            tracer.handle(&Event::CommandStarted {
+                command: command.clone(),
+                index,
+            });
+        }
+
+        for index in 0..2 {
+            tracer.handle(&Event::CommandExited {
+                command: command.clone(),
+                index,
+                status: ExitStatus::default(),
+            });
+        }
+
+        let spans = tracer.spans.lock().unwrap();
+
+        assert!(spans.open.is_empty());
+        assert_eq!(spans.finished.len(), 2);
+        assert_ne!(spans.finished[0].id, spans.finished[1].id);
+        assert!(spans
+            .finished
+            .iter()
+            .all(|span| span.name == "command npm test" && span.status == Some(STATUS_OK)));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::config::Config;
+use crate::constants::DEFAULT_MAX_ATTEMPTS;
+use crate::process::OutputSettings;
+use crate::server_management::ServerManager;
+
+pub struct TestEnvironment {
+    server_manager: ServerManager,
+}
+
+impl TestEnvironment {
+    pub fn launch(config: Config) -> anyhow::Result<TestEnvironment> {
+        let output_settings = OutputSettings::from_config(&config);
+        let environment = TestEnvironment {
+            server_manager: ServerManager::start(config, output_settings)?,
+        };
+
+        environment
+            .server_manager
+            .wait_for_servers(DEFAULT_MAX_ATTEMPTS)?;
+
+        Ok(environment)
+    }
+
+    pub fn url(&self, name: &str) -> Option<&str> {
+        self.server_manager
+            .config()
+            .servers
+            .iter()
+            .find(|s| s.name == name)?
+            .url
+            .as_deref()
+    }
+
+    pub fn port(&self, name: &str) -> Option<u16> {
+        reqwest::Url::parse(self.url(name)?)
+            .ok()?
+            .port_or_known_default()
+    }
+
+    pub fn server_manager(&self) -> &ServerManager {
+        &self.server_manager
+    }
+}
+
+impl Drop for TestEnvironment {
+    fn drop(&mut self) {
+        self.server_manager.stop().ok();
+    }
+}
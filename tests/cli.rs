@@ -51,3 +51,527 @@ fn fails_on_too_many_attempts_custom() {
             "Could not connect to server Hello World after 5 attempts",
         ));
 }
+
+#[test]
+fn writes_server_output_to_file() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command.arg("-c").arg("output_file.yaml").assert().success();
+
+    assert!(std::path::Path::new("target/logs/Hello World.log").exists());
+}
+
+#[test]
+fn records_run_history() {
+    std::fs::remove_file("target/history/history.jsonl").ok();
+
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command.arg("-c").arg("history.yaml").assert().success();
+
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("history.yaml")
+        .arg("history")
+        .arg("show")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello World: ready after"))
+        .stdout(predicate::str::contains("Command exit code: 0"));
+}
+
+#[test]
+fn runs_command_for_each_matrix_entry() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("matrix.yaml")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "printenv SHARD (BROWSER=chromium, SHARD=1): exit status: 0",
+        ))
+        .stderr(predicate::str::contains(
+            "printenv SHARD (BROWSER=firefox, SHARD=2): exit status: 0",
+        ));
+}
+
+#[test]
+fn runs_stages() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("stages.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("http://localhost:3020"))
+        .stdout(predicate::str::contains("stages.yaml"));
+}
+
+#[test]
+fn runs_parallel_commands() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("parallel_commands.yaml")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[urls] http://localhost:3022"))
+        .stderr(predicate::str::contains("1 of 2 parallel commands failed"));
+}
+
+#[test]
+fn benchmarks_time_to_ready() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("bench.yaml")
+        .arg("bench")
+        .arg("--runs")
+        .arg("2")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello World"))
+        .stdout(predicate::str::contains("all servers"));
+}
+
+#[test]
+fn injects_chaos_while_command_runs() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("chaos.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Chaos: restarting server Hello World",
+        ));
+}
+
+#[test]
+fn proxies_requests_to_servers() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("proxy.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("proxy_port: 3026"));
+}
+
+#[test]
+fn serves_static_files() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("static.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("type: \"static\""));
+}
+
+#[test]
+fn restarts_static_and_mock_servers() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("restart_in_process.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("restarted"))
+        .stdout(predicate::str::contains("name: \"Static\""));
+}
+
+#[test]
+fn serves_control_api() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("control_api.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"name\":\"Web\""))
+        .stdout(predicate::str::contains("Restarted server Web"))
+        .stdout(predicate::str::contains("cross-origin:403"))
+        .stdout(predicate::str::contains("still running"));
+}
+
+#[test]
+fn checks_tcp_ports() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("tcp.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tcp://localhost:3032"));
+}
+
+#[test]
+fn continues_without_nats() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("nats.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Could not connect to NATS nats://127.0.0.1:1, events are not published",
+        ))
+        .stdout(predicate::str::contains("done"));
+}
+
+#[test]
+fn serves_mock_routes() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("mock.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{\"name\":\"Jane\"}"));
+}
+
+#[test]
+fn waits_for_external_servers_from_config() {
+    let mut server = std::process::Command::new("simple-http-server")
+        .args(["-p", "3040", "-i", "-s"])
+        .spawn()
+        .unwrap();
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    let assert = command.arg("-c").arg("external.yaml").arg("wait").assert();
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+    assert.success();
+}
+
+#[test]
+fn wait_fails_on_unreachable_url() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-a")
+        .arg("2")
+        .arg("wait")
+        .arg("--url")
+        .arg("http://localhost:3029")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Could not connect to server http://localhost:3029 after 2 attempts",
+        ));
+}
+
+#[test]
+fn wait_rejects_invalid_url() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("wait")
+        .arg("--url")
+        .arg("localhost:3000")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Server localhost:3000 has an unsupported url scheme localhost",
+        ));
+}
+
+#[test]
+fn expands_placeholders_in_command() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("metadata.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{port:Hello World}"));
+}
+
+#[test]
+fn fails_on_exited_server() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("exited_server.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Last 1 lines of output from server Hello World",
+        ))
+        .stderr(predicate::str::contains("Server Hello World exited with"));
+}
+
+#[test]
+#[cfg(unix)]
+fn fails_on_server_exiting_before_command() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("died_server.yaml")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("died_server.yaml").not())
+        .stderr(predicate::str::contains("before the command was started"))
+        .stderr(predicate::str::contains("Server Short lived exited with"));
+}
+
+#[test]
+fn fails_on_exited_server_with_taken_port() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("port_taken.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Server Hello World exited with"));
+}
+
+#[test]
+fn rejects_zero_check_interval() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("zero_check_interval.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Server Hello World needs a check_interval above zero",
+        ));
+}
+
+#[test]
+fn fails_on_duplicate_server_names() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("duplicate_names.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Duplicate server name Hello World",
+        ));
+}
+
+#[test]
+fn quiet_servers_print_output_only_on_failure() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("exited_server.yaml")
+        .arg("--quiet-servers")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[Hello World]").not())
+        .stderr(predicate::str::contains(
+            "Last 1 lines of output from server Hello World",
+        ));
+}
+
+#[test]
+fn quiet_command_prints_output_on_failure() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("failing_command.yaml")
+        .arg("--quiet-command")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("failing_command.yaml").not())
+        .stderr(predicate::str::contains(
+            "Last 2 lines of output from command ls failing_command.yaml missing_file.yaml",
+        ));
+}
+
+#[test]
+fn fails_on_missing_program() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("missing_program.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Could not start server Typo"))
+        .stderr(predicate::str::contains(
+            "Could not start program simple-htpp-server",
+        ));
+}
+
+#[test]
+fn fails_on_invalid_url() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("invalid_url.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Server Hello World has an unsupported url scheme localhost",
+        ));
+}
+
+#[test]
+fn fails_on_failed_task() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("failing_task.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Task Migrations failed with exit status: 1",
+        ));
+}
+
+#[test]
+fn retries_checks_that_time_out() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("slow_server.yaml")
+        .arg("-a")
+        .arg("3")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Could not connect to server Slow after 3 attempts",
+        ));
+}
+
+#[test]
+fn fails_on_startup_timeout() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("startup_timeout.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Servers were not ready after 1.5s: Hello World (last check: waiting)",
+        ));
+}
+
+#[test]
+fn fails_on_missing_command_program() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("missing_command.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Could not start program no-such-test-runner",
+        ));
+}
+
+#[test]
+fn checks_exactly_max_attempts() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("max_attempts.yaml")
+        .arg("-v")
+        .arg("-a")
+        .arg("2")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("attempt 2"))
+        .stdout(predicate::str::contains("attempt 3").not())
+        .stderr(predicate::str::contains(
+            "Could not connect to server Hello World after 2 attempts",
+        ));
+}
+
+#[test]
+fn rejects_zero_attempts() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-a")
+        .arg("0")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '0'"));
+}
+
+#[test]
+fn exports_tmux_layout() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("servers.yaml")
+        .arg("export")
+        .arg("tmux")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "new-session -d -s \"$SESSION\" -n server-runner 'simple-http-server -p 3000 -i -s'",
+        ))
+        .stdout(predicate::str::contains("-T 'Hello World'"))
+        .stdout(predicate::str::contains(
+            "--config servers.yaml wait && sleep 5s'",
+        ));
+}
+
+#[test]
+fn accepts_attempts_beyond_u8() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("foobar.yaml")
+        .arg("-a")
+        .arg("1000")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Could not find config file foobar.yaml",
+        ));
+}
+
+#[cfg(windows)]
+#[test]
+fn stops_grandchildren_on_windows() {
+    let mut command = Command::cargo_bin("server-runner").unwrap();
+
+    command
+        .arg("-c")
+        .arg("windows_tree.yaml")
+        .assert()
+        .success();
+
+    assert!(std::net::TcpListener::bind(("127.0.0.1", 3014)).is_ok());
+}
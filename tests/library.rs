@@ -0,0 +1,84 @@
+use server_runner::{Config, TestEnvironment};
+
+#[test]
+fn launches_test_environment() {
+    let config = Config::load("test_environment.yaml").unwrap();
+    let environment = TestEnvironment::launch(config).unwrap();
+
+    assert_eq!(
+        environment.url("Hello World"),
+        Some("http://localhost:3006")
+    );
+    assert_eq!(environment.port("Hello World"), Some(3006));
+    assert!(reqwest::blocking::get("http://localhost:3006")
+        .unwrap()
+        .status()
+        .is_success());
+}
+
+#[cfg(unix)]
+#[test]
+fn calls_back_into_manager_on_crash() {
+    use server_runner::{Event, OutputSettings, ServerManager, ServerRunnerBuilder};
+    use std::sync::{mpsc, Arc, Mutex, OnceLock};
+    use std::time::Duration;
+
+    let config = Config::load("crash_handler.yaml").unwrap();
+    let mut output_settings = OutputSettings::from_config(&config);
+
+    output_settings.quiet_servers = true;
+
+    let manager: Arc<OnceLock<ServerManager>> = Arc::new(OnceLock::new());
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let handler_manager = Arc::clone(&manager);
+    let server_manager = ServerRunnerBuilder::new(config)
+        .output_settings(output_settings)
+        .on_event(move |event| {
+            if let Event::ServerCrashed { .. } = event {
+                let states = handler_manager.get().unwrap().status();
+
+                sender.lock().unwrap().send(states.len()).unwrap();
+            }
+        })
+        .start()
+        .unwrap();
+
+    manager.set(server_manager.clone()).ok();
+    server_manager.wait_for_servers(10).unwrap();
+
+    let pid = server_manager.status()[0].pid;
+
+    std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .status()
+        .unwrap();
+
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+
+    server_manager.stop().unwrap();
+}
+
+#[test]
+fn stops_servers_after_panicking_handler() {
+    use server_runner::{Event, ServerRunnerBuilder};
+    use std::panic::{self, AssertUnwindSafe};
+
+    let config = Config::load("panicking_handler.yaml").unwrap();
+    let server_manager = ServerRunnerBuilder::new(config)
+        .on_event(|event| {
+            if let Event::ServerReady { .. } = event {
+                panic!("handler failed");
+            }
+        })
+        .start()
+        .unwrap();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| server_manager.wait_for_servers(10)));
+
+    assert!(result.is_err());
+
+    server_manager.stop().unwrap();
+
+    assert!(reqwest::blocking::get("http://localhost:3035").is_err());
+}